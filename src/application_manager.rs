@@ -1,12 +1,139 @@
 pub mod gman {
     extern crate sfml;
 
-    use std::thread::JoinHandle;
+    use std::{thread::JoinHandle, fs, path::{Path, PathBuf}, time::Duration};
+
+    use yaml_rust::{Yaml, yaml::Hash};
 
     #[allow(unused_imports)]
     use sfml::{graphics::*, window::*, system::*};
 
-    use crate::{mandelbrot::Mandelbrot, gui::Gui};
+    use crate::{mandelbrot::Mandelbrot, gui::{Gui, Layout, Button, Slider, Checkbox, Label, TextInput, InputMode, Dropdown, RadioGroup}, lighting::Light, coloring::{ColorSettings, ColorMode, GrayscaleCurve, Layer, BlendMode, Transfer, TransferFunction, Antialiasing}, io::io, gradient::{Gradient, RepeatMode, Interpolation}, palette_io, palette_generator::{self, Harmony}, dither::Dithering, image_io::ExportFormat, color_vision::{self, Deficiency}, post::{PostSettings, Blur, Bloom, Unsharp, Vignette, Levels}, colorscheme::{self, Colorscheme}, watcher::FileWatcher};
+
+    const VIEW_FILE: &str = "view.yaml";
+    const PALETTE_DIRECTORY: &str = "palettes";
+    const MAX_REFINE_PASSES: u32 = 64;
+    const EXPORT_FILE: &str = "mandelbrot";
+    const COLORSCHEME_FILE: &str = "colorscheme.yaml";
+    const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+    const MIN_FONT_SIZE: u32 = 12;
+    const MAX_FONT_SIZE: u32 = 40;
+
+    // the gui only shows one coloring layer at a time, the others are kept here
+    #[derive(Clone)]
+    struct LayerControls {
+        transfer_function: TransferFunction,
+        density: f32,
+        offset: f32,
+        opacity: f32,
+        histogram: bool,
+        grayscale: bool,
+        grayscale_curve: GrayscaleCurve,
+        palette_index: Option<usize>,
+        repeat: RepeatMode,
+        interpolation: Interpolation,
+        blend: BlendMode,
+    }
+
+    impl LayerControls {
+        fn new() -> Self {
+            return LayerControls {
+                transfer_function: TransferFunction::Linear,
+                density: 50.0,
+                offset: 50.0,
+                opacity: 100.0,
+                histogram: false,
+                grayscale: false,
+                grayscale_curve: GrayscaleCurve::Linear,
+                palette_index: None,
+                repeat: RepeatMode::Repeat,
+                interpolation: Interpolation::Oklab,
+                blend: BlendMode::Normal,
+            };
+        }
+
+        fn transfer(&self) -> Transfer {
+            return Transfer::new(self.transfer_function, Gm::density_from_slider(self.density), (self.offset / 100.0).into());
+        }
+
+        fn set_transfer(&mut self, transfer: Transfer) {
+            self.transfer_function = transfer.function;
+            self.density = Gm::density_to_slider(transfer.density);
+            self.offset = (transfer.offset.rem_euclid(1.0) * 100.0) as f32;
+        }
+
+        // the palette is saved by name since the indices change with the palette directory
+        fn to_yaml(&self, palettes: &[Gradient]) -> Yaml {
+            let palette: &str = match self.palette_index {
+                Some(i) => &palettes[i].name,
+                None => "hsv",
+            };
+            let entries: Vec<(&str, Yaml)> = vec![
+                ("transfer", self.transfer().to_yaml()),
+                ("opacity", Yaml::Real(self.opacity.to_string())),
+                ("histogram", Yaml::Boolean(self.histogram)),
+                ("grayscale", Yaml::Boolean(self.grayscale)),
+                ("grayscale curve", Yaml::String(self.grayscale_curve.name().to_string())),
+                ("palette", Yaml::String(palette.to_string())),
+                ("repeat", Yaml::String(self.repeat.name().to_string())),
+                ("interpolation", Yaml::String(self.interpolation.name().to_string())),
+                ("blend", Yaml::String(self.blend.name().to_string())),
+            ];
+            let mut hash: Hash = Hash::new();
+            for (key, value) in entries {
+                hash.insert(Yaml::String(key.to_string()), value);
+            }
+            return Yaml::Hash(hash);
+        }
+
+        // missing or unknown values keep their defaults, a palette that is gone falls back to hsv
+        fn from_yaml(yaml: &Yaml, palettes: &[Gradient]) -> Self {
+            let mut layer: LayerControls = LayerControls::new();
+            if let Some(transfer) = Transfer::from_yaml(&yaml["transfer"]) {
+                layer.set_transfer(transfer);
+            }
+            if let Some(opacity) = yaml["opacity"].as_f64().or(yaml["opacity"].as_i64().map(|v| v as f64)) {
+                layer.opacity = (opacity as f32).clamp(0.0, 100.0);
+            }
+            layer.histogram = yaml["histogram"].as_bool().unwrap_or(layer.histogram);
+            layer.grayscale = yaml["grayscale"].as_bool().unwrap_or(layer.grayscale);
+            layer.grayscale_curve = yaml["grayscale curve"].as_str().and_then(GrayscaleCurve::from_name).unwrap_or(layer.grayscale_curve);
+            layer.palette_index = yaml["palette"].as_str().and_then(|name| palettes.iter().position(|p| p.name == name));
+            layer.repeat = yaml["repeat"].as_str().and_then(RepeatMode::from_name).unwrap_or(layer.repeat);
+            layer.interpolation = yaml["interpolation"].as_str().and_then(Interpolation::from_name).unwrap_or(layer.interpolation);
+            layer.blend = yaml["blend"].as_str().and_then(BlendMode::from_name).unwrap_or(layer.blend);
+            return layer;
+        }
+
+        fn mode(&self) -> ColorMode {
+            if self.grayscale {
+                return ColorMode::Grayscale(self.grayscale_curve);
+            }
+            if self.histogram {
+                return ColorMode::Histogram;
+            }
+            return ColorMode::Iteration;
+        }
+    }
+
+    // sent by the gui widgets and some keys, handled once a frame after the window events
+    enum Message {
+        // a control that is read back when the frame is colored again
+        Recolor,
+        CycleSpeed(f32),
+        Refine(bool),
+        RandomPalette,
+        NextHarmony,
+        SaveImage,
+        // saves the palette of the layer under the name in the palette name field
+        ExportPalette,
+        // typed into the text inputs
+        Seed(String),
+        Density(String),
+        // indices into the options of the choice widgets
+        Palette(usize),
+        Dithering(usize),
+    }
 
     pub struct Gm {
         pub window: RenderWindow,
@@ -30,45 +157,471 @@ pub mod gman {
             let mut do_gui: bool = true;
             let mut mandelbrot: Mandelbrot = Mandelbrot::new(self.window.size().x as i32, self.window.size().y as i32);
             let mut gui: Gui = Gui::new("fonts/Roboto-Regular.ttf", 24);
-            gui.add_slider(10.0, 40.0, 200.0, 0.0, 100.0);
-            gui.add_slider(10.0, 70.0, 200.0, 0.0, 100.0);
-            gui.add_slider(10.0, 100.0, 200.0, 0.0, 100.0);
-            gui.add_label(10.0, 130.0, "press R to regenerate colors".to_string());
-            gui.add_checkbox(10.0, 170.0);
-            gui.add_label(40.0, 160.0, "grayscale".to_string());
-            gui.add_label(10.0, 200.0, "press g to hide gui".to_string());
+            // the controls on the left, post effects and buttons on the right
+            gui.begin_container("panels", Layout::Padding { left: 10.0, top: 30.0, right: 10.0, bottom: 10.0 });
+            gui.begin_container("columns", Layout::Horizontal { spacing: 40.0 });
+            gui.begin_container("controls", Layout::Vertical { spacing: 2.0 });
+            gui.add_slider("value", 200.0, 0.0, 100.0);
+            gui.add_slider("saturation", 200.0, 0.0, 100.0);
+            gui.begin_container("density-row", Layout::Horizontal { spacing: 10.0 });
+            gui.add_slider("density", 200.0, 0.0, 100.0);
+            gui.add_label("density-label", "palette density".to_string());
+            gui.add_text_input("density-value", 130.0, InputMode::Scientific);
+            gui.end_container();
+            gui.add_label("random-hint", "press R for a random palette".to_string());
+            Gm::checkbox_row(&mut gui, "grayscale", "grayscale");
+            gui.add_label("gui-hint", "press g to hide gui, - and = for smaller or larger text".to_string());
+            Gm::checkbox_row(&mut gui, "lighting", "3D lighting");
+            Gm::slider_row(&mut gui, "light-angle", "light angle", 360.0);
+            Gm::slider_row(&mut gui, "light-height", "light height", 100.0);
+            Gm::slider_row(&mut gui, "specular", "specular", 100.0);
+            Gm::checkbox_row(&mut gui, "histogram", "histogram coloring");
+            Gm::slider_row(&mut gui, "offset", "palette offset", 100.0);
+            gui.add_label("transfer", String::new());
+            gui.add_label("view-hint", "press v to save the view".to_string());
+            gui.add_label("palette", String::new());
+            gui.begin_container("palette-export-row", Layout::Horizontal { spacing: 10.0 });
+            gui.add_label("palette-name-label", "export as".to_string());
+            gui.add_text_input("palette-name", 220.0, InputMode::Text);
+            gui.add_button("export-palette", 120.0, 30.0, "export (E)".to_string());
+            gui.end_container();
+            Gm::slider_row(&mut gui, "cycle-speed", "cycle speed", 100.0);
+            gui.add_label("cycle-hint", "press space to cycle colors, d to reverse".to_string());
+            gui.begin_container("seed-row", Layout::Horizontal { spacing: 10.0 });
+            gui.add_label("seed-label", "seed (R)".to_string());
+            gui.add_text_input("seed", 160.0, InputMode::Integer);
+            gui.add_label("harmony", String::new());
+            gui.end_container();
+            gui.add_label("antialiasing", String::new());
+            Gm::checkbox_row(&mut gui, "refine", "progressive refinement");
+            Gm::slider_row(&mut gui, "opacity", "layer opacity", 100.0);
+            gui.add_label("layer", String::new());
+            gui.add_label("layer-hint", "L adds a layer, K removes it, [ ] select, B blend".to_string());
+            gui.add_label("export", String::new());
+            gui.add_label("dithering", String::new());
+            gui.add_label("deficiency", String::new());
+            gui.add_label("theme", String::new());
+            gui.add_label("error", String::new());
+            gui.end_container();
+            gui.begin_container("effects", Layout::Vertical { spacing: 20.0 });
+            gui.begin_container("choices", Layout::Grid { columns: 2, spacing: 10.0 });
+            gui.add_label("palette-choice-label", "palette".to_string());
+            gui.add_dropdown("palette-choice", 250.0, Vec::new());
+            gui.add_label("dithering-choice-label", "dithering".to_string());
+            gui.add_radio_group("dithering-choice", Dithering::ALL.iter().map(|d| d.name().to_string()).collect());
+            gui.end_container();
+            // the controls and their labels line up in two columns
+            gui.begin_container("post", Layout::Grid { columns: 2, spacing: 10.0 });
+            gui.add_checkbox("blur");
+            gui.add_label("blur-label", "blur".to_string());
+            gui.add_slider("blur-radius", 200.0, 0.0, 100.0);
+            gui.add_label("blur-radius-label", "blur radius".to_string());
+            gui.add_checkbox("bloom");
+            gui.add_label("bloom-label", "bloom".to_string());
+            gui.add_slider("bloom-threshold", 200.0, 0.0, 100.0);
+            gui.add_label("bloom-threshold-label", "bloom threshold".to_string());
+            gui.add_slider("bloom-strength", 200.0, 0.0, 100.0);
+            gui.add_label("bloom-strength-label", "bloom strength".to_string());
+            gui.add_checkbox("unsharp");
+            gui.add_label("unsharp-label", "unsharp mask".to_string());
+            gui.add_slider("sharpen-amount", 200.0, 0.0, 100.0);
+            gui.add_label("sharpen-amount-label", "sharpen amount".to_string());
+            gui.add_checkbox("vignette");
+            gui.add_label("vignette-label", "vignette".to_string());
+            gui.add_slider("vignette-strength", 200.0, 0.0, 100.0);
+            gui.add_label("vignette-strength-label", "vignette strength".to_string());
+            gui.add_checkbox("levels");
+            gui.add_label("levels-label", "levels".to_string());
+            gui.add_slider("black-point", 200.0, 0.0, 100.0);
+            gui.add_label("black-point-label", "black point".to_string());
+            gui.add_slider("white-point", 200.0, 0.0, 100.0);
+            gui.add_label("white-point-label", "white point".to_string());
+            gui.add_slider("gamma", 200.0, 0.0, 100.0);
+            gui.add_label("gamma-label", "gamma".to_string());
+            gui.end_container();
+            gui.begin_container("buttons", Layout::Horizontal { spacing: 10.0 });
+            gui.add_button("random-palette", 200.0, 30.0, "random palette".to_string());
+            gui.add_button("save-image", 200.0, 30.0, "save image".to_string());
+            gui.end_container();
+            gui.end_container();
+            gui.end_container();
+            gui.end_container();
+            gui.get_mut::<Slider>("black-point").set_value(0.0);
+            gui.get_mut::<Slider>("white-point").set_value(100.0);
+            let recolor_sliders: [&str; 16] = ["value", "saturation", "density", "light-angle", "light-height", "specular", "offset", "opacity",
+                "blur-radius", "bloom-threshold", "bloom-strength", "sharpen-amount", "vignette-strength", "black-point", "white-point", "gamma"];
+            for id in recolor_sliders.iter().copied() {
+                gui.get_mut::<Slider>(id).on_change(|_| Message::Recolor);
+            }
+            for id in ["grayscale", "lighting", "histogram", "blur", "bloom", "unsharp", "vignette", "levels"].iter().copied() {
+                gui.get_mut::<Checkbox>(id).on_change(|_| Message::Recolor);
+            }
+            gui.get_mut::<Slider>("cycle-speed").on_change(Message::CycleSpeed);
+            gui.get_mut::<Checkbox>("refine").on_change(Message::Refine);
+            gui.get_mut::<Button>("random-palette").on_click(|| Message::RandomPalette);
+            gui.get_mut::<Button>("save-image").on_click(|| Message::SaveImage);
+            gui.get_mut::<Button>("export-palette").on_click(|| Message::ExportPalette);
+            gui.get_mut::<TextInput>("seed").on_change(Message::Seed);
+            gui.get_mut::<TextInput>("density-value").on_change(Message::Density);
+            gui.get_mut::<Dropdown>("palette-choice").on_change(Message::Palette);
+            gui.get_mut::<RadioGroup>("dithering-choice").on_change(Message::Dithering);
             let clock: sfml::SfBox<Clock> = Clock::start();
             let mut prev_time: Time = clock.elapsed_time();
             let mut current_time: Time;
             let mut thread: Option<JoinHandle<Vec<u8>>> = None;
             let mut is_thread_done: bool = true;
             let mut regen_colors: bool = false;
+            let mut layers: Vec<LayerControls> = vec![LayerControls::new()];
+            let mut selected: usize = 0;
+            let mut palettes: Vec<Gradient> = Gradient::builtin();
+            palettes.append(&mut color_vision::safe_palettes());
+            // the file every palette was loaded from, None for built in and generated ones
+            let mut palette_files: Vec<Option<PathBuf>> = vec![None; palettes.len()];
+            // palette and theme errors at startup are shown together, one per line
+            let (loaded, mut startup_errors) = palette_io::load_directory(PALETTE_DIRECTORY);
+            startup_errors.iter_mut().for_each(|e| *e = format!("error loading palette {}", e));
+            for (path, gradient) in loaded {
+                palettes.push(gradient);
+                palette_files.push(Some(path));
+            }
+            // view files from before the layers were saved only have the transfer of one layer
+            if let Some(view) = io::read_yaml(VIEW_FILE) {
+                match view["layers"].as_vec() {
+                    Some(saved) if !saved.is_empty() => layers = saved.iter().map(|yaml| LayerControls::from_yaml(yaml, &palettes)).collect(),
+                    _ => {
+                        if let Some(transfer) = Transfer::from_yaml(&view["transfer"]) {
+                            layers[0].set_transfer(transfer);
+                        }
+                    }
+                }
+                selected = view["selected"].as_i64().map_or(0, |i| (i.max(0) as usize).min(layers.len() - 1));
+                regen_colors = true;
+            }
+            let mut cycling: bool = false;
+            let mut cycle_direction: f64 = 1.0;
+            let mut cycle_offset: f64 = 0.0;
+            let mut cycle_speed: f32 = gui.get::<Slider>("cycle-speed").value;
+            let mut refine: bool = false;
+            Gm::show_layer(&mut gui, &layers, selected, &palettes);
+            let mut seed: u32 = rand::random();
+            let mut harmony: Harmony = Harmony::Analogous;
+            let mut generated_index: Option<usize> = None;
+            let mut antialiasing: Antialiasing = Antialiasing::Off;
+            gui.get_mut::<Label>("antialiasing").set_text(format!("antialiasing: {} (A)", antialiasing.name()));
+            let mut export_format: ExportFormat = ExportFormat::Png8;
+            gui.get_mut::<Label>("export").set_text(format!("press s to save the image as {} (F)", export_format.name()));
+            let mut dithering: Dithering = Dithering::Off;
+            gui.get_mut::<Label>("dithering").set_text(format!("dithering: {} (O)", dithering.name()));
+            let mut deficiency: Deficiency = Deficiency::Normal;
+            gui.get_mut::<Label>("deficiency").set_text(format!("color blind preview: {} (U)", deficiency.name()));
+            // the built in scheme stays if the theme file can't be read
+            let mut themes: Vec<Colorscheme> = Vec::new();
+            let mut theme_index: usize = 0;
+            match Gm::reload_themes(&mut gui, &mut themes, &mut theme_index) {
+                Ok(_) => (),
+                Err(e) => {
+                    gui.get_mut::<Label>("theme").set_text(Gm::theme_label(&themes, theme_index));
+                    startup_errors.push(e);
+                }
+            };
+            gui.get_mut::<Label>("error").set_text(startup_errors.join("\n"));
+            // edited files are reloaded, a file that doesn't parse keeps the last version that did
+            let mut watcher: FileWatcher = FileWatcher::new(WATCH_INTERVAL);
+            watcher.watch(COLORSCHEME_FILE);
+            watcher.watch_directory(PALETTE_DIRECTORY);
+            let mut last_settings: Option<ColorSettings> = None;
+            let mut refine_pass: u32 = 0;
+            Gm::show_seed(&mut gui, seed, harmony);
 
             while self.window.is_open() {
                 while let Some(event) = self.window.poll_event() {
+                    // a click on a widget is not also a click on the fractal
+                    if do_gui && gui.handle_event(&event) {
+                        continue;
+                    }
                     match event {
                         Event::Closed => self.window.close(),
                         Event::KeyPressed { code: Key::Escape, alt: false, ctrl: false, shift: false, system: false, .. } => self.window.close(),
                         Event::KeyReleased { code, ..} => {
                             if code == Key::R {
-                                regen_colors = true;
+                                gui.send(Message::RandomPalette);
+                            }
+                            if code == Key::H {
+                                gui.send(Message::NextHarmony);
                             }
                             if code == Key::G {
                                 do_gui = !do_gui;
+                                // the hidden gui gets no events, so it would never see the releases
+                                gui.cancel_input();
+                            }
+                            // the layout follows on the next update
+                            if code == Key::Hyphen {
+                                gui.set_font_size(gui.font_size().saturating_sub(2).max(MIN_FONT_SIZE));
+                            }
+                            if code == Key::Equal {
+                                gui.set_font_size((gui.font_size() + 2).min(MAX_FONT_SIZE));
+                            }
+                            if code == Key::A {
+                                antialiasing = antialiasing.next();
+                                gui.get_mut::<Label>("antialiasing").set_text(format!("antialiasing: {} (A)", antialiasing.name()));
+                                regen_colors = true;
+                            }
+                            if code == Key::O {
+                                dithering = dithering.next();
+                                gui.get_mut::<Label>("dithering").set_text(format!("dithering: {} (O)", dithering.name()));
+                                gui.get_mut::<RadioGroup>("dithering-choice").set_selected(Dithering::ALL.iter().position(|d| *d == dithering).unwrap());
+                                regen_colors = true;
+                            }
+                            if code == Key::U {
+                                deficiency = deficiency.next();
+                                gui.get_mut::<Label>("deficiency").set_text(format!("color blind preview: {} (U)", deficiency.name()));
+                                regen_colors = true;
+                            }
+                            if code == Key::N {
+                                if !themes.is_empty() {
+                                    theme_index = (theme_index + 1) % themes.len();
+                                    gui.set_colorscheme(themes[theme_index].clone());
+                                }
+                                gui.get_mut::<Label>("theme").set_text(Gm::theme_label(&themes, theme_index));
+                            }
+                            if code == Key::Space {
+                                cycling = !cycling;
+                            }
+                            if code == Key::D {
+                                cycle_direction = -cycle_direction;
+                            }
+                            if code == Key::T {
+                                layers[selected].transfer_function = layers[selected].transfer_function.next();
+                                gui.get_mut::<Label>("transfer").set_text(format!("transfer: {} (press T)", layers[selected].transfer_function.name()));
+                                regen_colors = true;
+                            }
+                            if code == Key::P || code == Key::M || code == Key::I {
+                                let layer: &mut LayerControls = &mut layers[selected];
+                                match code {
+                                    Key::P => layer.palette_index = match layer.palette_index {
+                                        None => Some(0),
+                                        Some(i) if i + 1 < palettes.len() => Some(i + 1),
+                                        Some(_) => None,
+                                    },
+                                    Key::M => layer.repeat = layer.repeat.next(),
+                                    _ => layer.interpolation = layer.interpolation.next(),
+                                }
+                                Gm::show_palette(&mut gui, &palettes, layer);
+                                regen_colors = true;
+                            }
+                            if code == Key::E {
+                                gui.send(Message::ExportPalette);
+                            }
+                            if code == Key::L || code == Key::K || code == Key::LBracket || code == Key::RBracket {
+                                Gm::read_layer(&gui, &mut layers[selected]);
+                                match code {
+                                    Key::L => {
+                                        layers.insert(selected + 1, LayerControls::new());
+                                        selected += 1;
+                                    }
+                                    Key::K if layers.len() > 1 => {
+                                        layers.remove(selected);
+                                        selected = selected.min(layers.len() - 1);
+                                    }
+                                    Key::LBracket => selected = selected.saturating_sub(1),
+                                    Key::RBracket => selected = (selected + 1).min(layers.len() - 1),
+                                    _ => (),
+                                }
+                                Gm::show_layer(&mut gui, &layers, selected, &palettes);
+                                regen_colors = true;
+                            }
+                            if code == Key::C {
+                                layers[selected].grayscale_curve = layers[selected].grayscale_curve.next();
+                                Gm::show_layer(&mut gui, &layers, selected, &palettes);
+                                regen_colors = true;
+                            }
+                            if code == Key::F {
+                                export_format = export_format.next();
+                                gui.get_mut::<Label>("export").set_text(format!("press s to save the image as {} (F)", export_format.name()));
+                            }
+                            if code == Key::S {
+                                gui.send(Message::SaveImage);
+                            }
+                            if code == Key::B {
+                                layers[selected].blend = layers[selected].blend.next();
+                                Gm::show_layer(&mut gui, &layers, selected, &palettes);
+                                regen_colors = true;
+                            }
+                            if code == Key::V {
+                                Gm::read_layer(&gui, &mut layers[selected]);
+                                let mut view: Hash = Hash::new();
+                                view.insert(Yaml::String("layers".to_string()), Yaml::Array(layers.iter().map(|layer| layer.to_yaml(&palettes)).collect()));
+                                view.insert(Yaml::String("selected".to_string()), Yaml::Integer(selected as i64));
+                                match io::write_yaml(VIEW_FILE, &Yaml::Hash(view)) {
+                                    Ok(_) => gui.get_mut::<Label>("error").set_text(String::new()),
+                                    Err(e) => gui.get_mut::<Label>("error").set_text(format!("error saving view: {}", e)),
+                                };
                             }
                         }
                         _ => {}
                     }
                 }
             
-                let mouse_pos: Vector2i = mouse::desktop_position();
-                let mouse_pos: Vector2f = Vector2f::new((mouse_pos.x - self.window.position().x) as f32, (mouse_pos.y - self.window.position().y) as f32);
-                gui.update(mouse_pos.x as i32, mouse_pos.y as i32, (mouse::Button::Left.is_pressed(), mouse::Button::Middle.is_pressed(), mouse::Button::Right.is_pressed()));
+                for path in watcher.poll() {
+                    let reloaded: Result<(), String> = if path == Path::new(COLORSCHEME_FILE) {
+                        Gm::reload_themes(&mut gui, &mut themes, &mut theme_index)
+                    } else {
+                        match Gm::reload_palette(&mut palettes, &mut palette_files, &path) {
+                            Ok(remap) => {
+                                // indices move when a file gains or loses gradients
+                                layers.iter_mut().for_each(|layer| layer.palette_index = layer.palette_index.and_then(|i| remap[i]));
+                                generated_index = generated_index.and_then(|i| remap[i]);
+                                Gm::show_layer(&mut gui, &layers, selected, &palettes);
+                                regen_colors = true;
+                                Ok(())
+                            }
+                            Err(e) => Err(format!("error loading palette {}: {}", path.display(), e)),
+                        }
+                    };
+                    match reloaded {
+                        Ok(_) => gui.get_mut::<Label>("error").set_text(String::new()),
+                        Err(e) => gui.get_mut::<Label>("error").set_text(e),
+                    };
+                }
 
-                if gui.slider_components[0].get_value_changed() || gui.slider_components[1].get_value_changed() || gui.slider_components[2].get_value_changed() || regen_colors {
-                    thread = Some(mandelbrot.set_color(gui.slider_components[0].value, gui.slider_components[1].value, gui.slider_components[2].value.into(), gui.checkbox_components[0].state));
+                gui.layout();
+
+                for message in gui.take_messages::<Message>() {
+                    match message {
+                        Message::Recolor => {
+                            Gm::show_density(&mut gui);
+                            regen_colors = true;
+                        }
+                        Message::CycleSpeed(speed) => cycle_speed = speed,
+                        Message::Refine(on) => refine = on,
+                        Message::RandomPalette | Message::NextHarmony | Message::Seed(_) => {
+                            match &message {
+                                Message::RandomPalette => seed = rand::random(),
+                                Message::Seed(text) => match text.parse::<u32>() {
+                                    Ok(typed) => {
+                                        seed = typed;
+                                        gui.get_mut::<Label>("error").set_text(String::new());
+                                    }
+                                    Err(_) => {
+                                        gui.get_mut::<Label>("error").set_text(format!("the seed must be a whole number from 0 to {}", u32::MAX));
+                                        Gm::show_seed(&mut gui, seed, harmony);
+                                        continue;
+                                    }
+                                },
+                                _ => harmony = harmony.next(),
+                            }
+                            // the generated palette keeps one slot in the palette list, press E to keep it
+                            let gradient: Gradient = palette_generator::generate(seed, harmony);
+                            match generated_index {
+                                Some(i) => palettes[i] = gradient,
+                                None => {
+                                    palettes.push(gradient);
+                                    palette_files.push(None);
+                                    generated_index = Some(palettes.len() - 1);
+                                }
+                            }
+                            layers[selected].palette_index = generated_index;
+                            Gm::show_palette(&mut gui, &palettes, &layers[selected]);
+                            Gm::show_seed(&mut gui, seed, harmony);
+                            regen_colors = true;
+                        }
+                        // the name comes from the field, the file name is cut down to characters that are safe in a path
+                        // the yaml is written first so the palette directory never loads the map on its own
+                        Message::ExportPalette => {
+                            if let Some(mut gradient) = Gm::selected_gradient(&palettes, &layers[selected]) {
+                                let name: String = gui.get::<TextInput>("palette-name").text().trim().to_string();
+                                if !name.is_empty() {
+                                    gradient.name = name;
+                                }
+                                let exported: Result<PathBuf, String> = match palette_io::file_name(&gradient.name) {
+                                    Some(file) => {
+                                        let path: PathBuf = Path::new(PALETTE_DIRECTORY).join(file);
+                                        fs::create_dir_all(PALETTE_DIRECTORY)
+                                            .and_then(|_| palette_io::save_yaml(path.with_extension("yaml"), &gradient))
+                                            .and_then(|_| palette_io::save_map(path.with_extension("map"), &gradient))
+                                            .map(|_| path.with_extension("yaml"))
+                                            .map_err(|e| format!("error exporting palette: {}", e))
+                                    }
+                                    None => Err(format!("\"{}\" can't be used as a file name", gradient.name)),
+                                };
+                                match exported {
+                                    Ok(path) => {
+                                        // a kept generated palette is now the one of the file, the reload replaces it instead of adding it
+                                        if let Some(i) = generated_index.filter(|i| layers[selected].palette_index == Some(*i)) {
+                                            palettes[i].name = gradient.name.clone();
+                                            palette_files[i] = Some(path);
+                                            generated_index = None;
+                                        }
+                                        gui.get_mut::<Label>("error").set_text(String::new());
+                                    }
+                                    Err(e) => gui.get_mut::<Label>("error").set_text(e),
+                                };
+                            }
+                        }
+                        Message::SaveImage => {
+                            match mandelbrot.export(Path::new(EXPORT_FILE).with_extension(export_format.extension()), export_format) {
+                                Ok(_) => gui.get_mut::<Label>("error").set_text(String::new()),
+                                Err(e) => gui.get_mut::<Label>("error").set_text(format!("error saving image: {}", e)),
+                            };
+                        }
+                        // outside of the slider range it is clamped, the field then shows what was used
+                        Message::Density(text) => {
+                            match text.parse::<f64>() {
+                                Ok(density) if density > 0.0 => {
+                                    gui.get_mut::<Slider>("density").set_value(Gm::density_to_slider(density));
+                                    gui.get_mut::<Label>("error").set_text(String::new());
+                                    regen_colors = true;
+                                }
+                                _ => gui.get_mut::<Label>("error").set_text("the palette density must be above 0".to_string()),
+                            }
+                            Gm::show_density(&mut gui);
+                        }
+                        // the first option is the hsv hue rotation, the others are the palette list
+                        Message::Palette(index) => {
+                            layers[selected].palette_index = index.checked_sub(1);
+                            Gm::show_palette(&mut gui, &palettes, &layers[selected]);
+                            regen_colors = true;
+                        }
+                        Message::Dithering(index) => {
+                            dithering = Dithering::ALL[index];
+                            gui.get_mut::<Label>("dithering").set_text(format!("dithering: {} (O)", dithering.name()));
+                            regen_colors = true;
+                        }
+                    }
+                }
+
+                let mut settings_changed: bool = false;
+                // cycling only moves the palette offset, a new frame is started as soon as the last one is done
+                if cycling {
+                    let speed: f64 = (cycle_speed / 100.0).into();
+                    cycle_offset = (cycle_offset + speed * cycle_direction * (clock.elapsed_time().as_seconds() - prev_time.as_seconds()) as f64).rem_euclid(1.0);
+                    settings_changed |= is_thread_done;
+                }
+                if settings_changed || regen_colors {
+                    let mut light: Light = Light::new(gui.get::<Slider>("light-angle").value.into(), (gui.get::<Slider>("light-height").value / 100.0).into(), (gui.get::<Slider>("specular").value / 100.0).into());
+                    light.enabled = gui.get::<Checkbox>("lighting").state;
+                    Gm::read_layer(&gui, &mut layers[selected]);
+                    let color_layers: Vec<Layer> = layers.iter().map(|controls| {
+                        let mut transfer: Transfer = controls.transfer();
+                        transfer.offset += cycle_offset;
+                        let mut layer: Layer = Layer::new(controls.mode(), Gm::selected_gradient(&palettes, controls), transfer);
+                        layer.opacity = (controls.opacity / 100.0).into();
+                        layer.blend = controls.blend;
+                        layer
+                    }).collect();
+                    let settings: ColorSettings = ColorSettings { value: gui.get::<Slider>("value").value, saturation: gui.get::<Slider>("saturation").value, layers: color_layers, light, antialiasing, dithering, deficiency, post: Gm::post_from_gui(&gui) };
+                    thread = Some(mandelbrot.set_color(settings.clone()));
+                    last_settings = Some(settings);
+                    refine_pass = 1;
                     is_thread_done = false;
                     regen_colors = false;
+                } else if refine && is_thread_done && refine_pass < MAX_REFINE_PASSES {
+                    // the view is still, keep adding samples
+                    if let Some(settings) = &last_settings {
+                        thread = Some(mandelbrot.refine(settings.clone(), refine_pass));
+                        refine_pass += 1;
+                        is_thread_done = false;
+                    }
                 }
 
                 if is_thread_done == false {
@@ -97,5 +650,150 @@ pub mod gman {
                 prev_time = current_time;
             }
         }
+
+        // a slider with its label to the right
+        fn slider_row(gui: &mut Gui, id: &str, text: &str, max_value: f32) {
+            gui.begin_container(&format!("{}-row", id), Layout::Horizontal { spacing: 10.0 });
+            gui.add_slider(id, 200.0, 0.0, max_value);
+            gui.add_label(&format!("{}-label", id), text.to_string());
+            gui.end_container();
+        }
+
+        fn checkbox_row(gui: &mut Gui, id: &str, text: &str) {
+            gui.begin_container(&format!("{}-row", id), Layout::Horizontal { spacing: 10.0 });
+            gui.add_checkbox(id);
+            gui.add_label(&format!("{}-label", id), text.to_string());
+            gui.end_container();
+        }
+
+        // the density slider is logarithmic, its center is a density of 0.1
+        fn density_from_slider(value: f32) -> f64 {
+            return 10.0_f64.powf((value as f64 - 50.0) / 20.0) / 10.0;
+        }
+
+        fn density_to_slider(density: f64) -> f32 {
+            return (50.0 + 20.0 * (density * 10.0).log10()).clamp(0.0, 100.0) as f32;
+        }
+
+        fn selected_gradient(palettes: &[Gradient], layer: &LayerControls) -> Option<Gradient> {
+            return layer.palette_index.map(|i| {
+                let mut gradient: Gradient = palettes[i].clone();
+                gradient.repeat = layer.repeat;
+                gradient.interpolation = layer.interpolation;
+                gradient
+            });
+        }
+
+        fn palette_label(palettes: &[Gradient], layer: &LayerControls) -> String {
+            let name: &str = match layer.palette_index {
+                Some(i) => &palettes[i].name,
+                None => "hsv",
+            };
+            return format!("palette: {} (P), {} (M), {} (I)", name, layer.repeat.name(), layer.interpolation.name());
+        }
+
+        // stores the per layer controls of the gui in the layer
+        fn read_layer(gui: &Gui, layer: &mut LayerControls) {
+            layer.density = gui.get::<Slider>("density").value;
+            layer.offset = gui.get::<Slider>("offset").value;
+            layer.opacity = gui.get::<Slider>("opacity").value;
+            layer.grayscale = gui.get::<Checkbox>("grayscale").state;
+            layer.histogram = gui.get::<Checkbox>("histogram").state;
+        }
+
+        // keeps showing the same scheme if it is still in the file
+        fn reload_themes(gui: &mut Gui, themes: &mut Vec<Colorscheme>, theme_index: &mut usize) -> Result<(), String> {
+            let loaded: Vec<Colorscheme> = match colorscheme::load(COLORSCHEME_FILE) {
+                Ok(x) => x,
+                Err(e) => return Err(format!("error loading {}: {}", COLORSCHEME_FILE, e)),
+            };
+            let current: Option<&String> = themes.get(*theme_index).map(|theme| &theme.name);
+            *theme_index = loaded.iter().position(|theme| Some(&theme.name) == current).unwrap_or(0);
+            *themes = loaded;
+            gui.set_colorscheme(themes[*theme_index].clone());
+            gui.get_mut::<Label>("theme").set_text(Gm::theme_label(themes, *theme_index));
+            return Ok(());
+        }
+
+        // replaces the gradients that came from path, a removed file drops its gradients and so does a map
+        // that a yaml of the same palette shadows. returns the new index of every old palette index, None for dropped ones
+        fn reload_palette(palettes: &mut Vec<Gradient>, palette_files: &mut Vec<Option<PathBuf>>, path: &Path) -> Result<Vec<Option<usize>>, palette_io::PaletteError> {
+            let mut loaded = match path.exists() && !palette_io::is_shadowed(path) {
+                true => palette_io::load(path)?.into_iter(),
+                false => Vec::new().into_iter(),
+            };
+            let mut remap: Vec<Option<usize>> = Vec::with_capacity(palettes.len());
+            let mut gradients: Vec<Gradient> = Vec::with_capacity(palettes.len());
+            let mut files: Vec<Option<PathBuf>> = Vec::with_capacity(palettes.len());
+            for (gradient, file) in palettes.drain(..).zip(palette_files.drain(..)) {
+                let gradient: Option<Gradient> = if file.as_deref() == Some(path) { loaded.next() } else { Some(gradient) };
+                remap.push(gradient.as_ref().map(|_| gradients.len()));
+                if let Some(gradient) = gradient {
+                    gradients.push(gradient);
+                    files.push(file);
+                }
+            }
+            for gradient in loaded {
+                gradients.push(gradient);
+                files.push(Some(path.to_path_buf()));
+            }
+            *palettes = gradients;
+            *palette_files = files;
+            return Ok(remap);
+        }
+
+        fn theme_label(themes: &[Colorscheme], theme_index: usize) -> String {
+            let name: &str = match themes.get(theme_index) {
+                Some(theme) => &theme.name,
+                None => "built in",
+            };
+            return format!("theme: {} (N)", name);
+        }
+
+        fn post_from_gui(gui: &Gui) -> PostSettings {
+            let slider = |id: &str| -> f64 { (gui.get::<Slider>(id).value / 100.0).into() };
+            let enabled = |id: &str| -> bool { gui.get::<Checkbox>(id).state };
+            return PostSettings {
+                blur: Some(Blur { radius: slider("blur-radius") * 10.0 }).filter(|_| enabled("blur")),
+                bloom: Some(Bloom { threshold: slider("bloom-threshold"), radius: 12.0, strength: slider("bloom-strength") * 2.0 }).filter(|_| enabled("bloom")),
+                unsharp: Some(Unsharp { radius: 2.0, amount: slider("sharpen-amount") * 2.0 }).filter(|_| enabled("unsharp")),
+                vignette: Some(Vignette { strength: slider("vignette-strength"), radius: 0.4 }).filter(|_| enabled("vignette")),
+                // the gamma slider is logarithmic from 0.1 to 10
+                levels: Some(Levels { black: slider("black-point") * 0.5, white: 0.5 + slider("white-point") * 0.5, gamma: 10.0_f64.powf(slider("gamma") * 2.0 - 1.0) }).filter(|_| enabled("levels")),
+            };
+        }
+
+        fn show_layer(gui: &mut Gui, layers: &[LayerControls], selected: usize, palettes: &[Gradient]) {
+            let layer: &LayerControls = &layers[selected];
+            gui.get_mut::<Slider>("density").set_value(layer.density);
+            gui.get_mut::<Slider>("offset").set_value(layer.offset);
+            gui.get_mut::<Slider>("opacity").set_value(layer.opacity);
+            gui.get_mut::<Checkbox>("grayscale").set_state(layer.grayscale);
+            gui.get_mut::<Checkbox>("histogram").set_state(layer.histogram);
+            gui.get_mut::<Label>("grayscale-label").set_text(format!("grayscale: {} (C)", layer.grayscale_curve.name()));
+            gui.get_mut::<Label>("transfer").set_text(format!("transfer: {} (press T)", layer.transfer_function.name()));
+            Gm::show_palette(gui, palettes, layer);
+            gui.get_mut::<Label>("layer").set_text(format!("layer {}/{}: {}", selected + 1, layers.len(), layer.blend.name()));
+            Gm::show_density(gui);
+        }
+
+        // the label and the dropdown, which lists the palettes as they are now
+        fn show_palette(gui: &mut Gui, palettes: &[Gradient], layer: &LayerControls) {
+            gui.get_mut::<Label>("palette").set_text(Gm::palette_label(palettes, layer));
+            let name: String = layer.palette_index.map_or(String::new(), |i| palettes[i].name.clone());
+            gui.get_mut::<TextInput>("palette-name").set_text(name);
+            let options: Vec<String> = std::iter::once("hsv".to_string()).chain(palettes.iter().map(|p| p.name.clone())).collect();
+            gui.get_mut::<Dropdown>("palette-choice").set_options(options, layer.palette_index.map_or(0, |i| i + 1));
+        }
+
+        fn show_density(gui: &mut Gui) {
+            let density: f64 = Gm::density_from_slider(gui.get::<Slider>("density").value);
+            gui.get_mut::<TextInput>("density-value").set_text(format!("{:.3e}", density));
+        }
+
+        fn show_seed(gui: &mut Gui, seed: u32, harmony: Harmony) {
+            gui.get_mut::<TextInput>("seed").set_text(seed.to_string());
+            gui.get_mut::<Label>("harmony").set_text(format!("{} (H)", harmony.name()));
+        }
     }
 }
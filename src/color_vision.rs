@@ -0,0 +1,143 @@
+use crate::{gradient::{Gradient, ColorStop}, math::color::LinearRgb};
+
+// simulated color vision deficiency for previewing the frame, Normal shows it unchanged
+#[derive(Clone, Copy, PartialEq)]
+pub enum Deficiency {
+    Normal,
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+// machado, oliveira and fernandes 2009 at full severity, applied to linear rgb
+const PROTANOPIA: [[f64; 3]; 3] = [
+    [0.152286, 1.052583, -0.204868],
+    [0.114503, 0.786281, 0.099216],
+    [-0.003882, -0.048116, 1.051998],
+];
+const DEUTERANOPIA: [[f64; 3]; 3] = [
+    [0.367322, 0.860646, -0.227968],
+    [0.280085, 0.672501, 0.047413],
+    [-0.011820, 0.042940, 0.968881],
+];
+const TRITANOPIA: [[f64; 3]; 3] = [
+    [1.255528, -0.076749, -0.178779],
+    [-0.078411, 0.930809, 0.147602],
+    [0.004733, 0.691367, 0.303900],
+];
+
+impl Deficiency {
+    pub const ALL: [Deficiency; 4] = [Deficiency::Normal, Deficiency::Protanopia, Deficiency::Deuteranopia, Deficiency::Tritanopia];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Deficiency::Normal => "off",
+            Deficiency::Protanopia => "protanopia",
+            Deficiency::Deuteranopia => "deuteranopia",
+            Deficiency::Tritanopia => "tritanopia",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        let index: usize = Deficiency::ALL.iter().position(|d| d == self).unwrap();
+        return Deficiency::ALL[(index + 1) % Deficiency::ALL.len()];
+    }
+
+    pub fn simulate(&self, c: LinearRgb) -> LinearRgb {
+        let m: &[[f64; 3]; 3] = match self {
+            Deficiency::Normal => return c,
+            Deficiency::Protanopia => &PROTANOPIA,
+            Deficiency::Deuteranopia => &DEUTERANOPIA,
+            Deficiency::Tritanopia => &TRITANOPIA,
+        };
+        let row = |r: &[f64; 3]| -> f64 { r[0] * c.r + r[1] * c.g + r[2] * c.b };
+        return LinearRgb::new(row(&m[0]), row(&m[1]), row(&m[2]));
+    }
+
+    // a pixel of the float frame, alpha is kept
+    pub fn simulate_pixel(&self, p: [f32; 4]) -> [f32; 4] {
+        let c: LinearRgb = self.simulate(LinearRgb::new(p[0] as f64, p[1] as f64, p[2] as f64));
+        return [c.r as f32, c.g as f32, c.b as f32, p[3]];
+    }
+}
+
+// palettes that stay readable with every deficiency above, checked by the tests below.
+// the hsv hue rotation is not one of them, red and green bands merge for most color blind viewers
+pub fn safe_palettes() -> Vec<Gradient> {
+    let stops = |colors: &[(u8, u8, u8)]| -> Vec<ColorStop> {
+        let last: f64 = (colors.len() - 1) as f64;
+        colors.iter().enumerate().map(|(i, c)| ColorStop::new(i as f64 / last, c.0, c.1, c.2)).collect()
+    };
+    return vec![
+        Gradient::new("viridis", stops(&[
+            (68, 1, 84), (71, 44, 122), (59, 81, 139), (44, 113, 142), (33, 144, 141),
+            (39, 173, 129), (92, 200, 99), (170, 220, 50), (253, 231, 37),
+        ])),
+        Gradient::new("cividis", stops(&[(0, 32, 77), (65, 77, 107), (124, 123, 120), (188, 175, 111), (255, 234, 70)])),
+        Gradient::new("blue-orange", stops(&[
+            (0, 48, 100), (0, 114, 178), (86, 180, 233), (240, 240, 240), (240, 180, 60), (213, 94, 0), (110, 40, 0),
+        ])),
+    ];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::color::{Rgb, Oklab};
+
+    fn simulate_rgb(deficiency: Deficiency, c: Rgb) -> Rgb {
+        return Rgb::from(deficiency.simulate(LinearRgb::from(c))).clamped();
+    }
+
+    fn distance(a: Oklab, b: Oklab) -> f64 {
+        return ((a.l - b.l).powi(2) + (a.a - b.a).powi(2) + (a.b - b.b).powi(2)).sqrt();
+    }
+
+    // smallest oklab distance between any two of 9 evenly spaced samples as seen with the deficiency
+    fn min_distance(gradient: &Gradient, deficiency: Deficiency) -> f64 {
+        let samples: Vec<Oklab> = (0..9).map(|i| {
+            let (r, g, b) = gradient.sample(i as f64 / 8.0 * 0.999);
+            Oklab::from(simulate_rgb(deficiency, Rgb::from_u8(r, g, b)))
+        }).collect();
+        let mut min: f64 = f64::INFINITY;
+        for i in 0..samples.len() {
+            for j in i + 1..samples.len() {
+                min = min.min(distance(samples[i], samples[j]));
+            }
+        }
+        return min;
+    }
+
+    #[test]
+    fn safe_palettes_stay_distinguishable() {
+        for gradient in safe_palettes() {
+            for deficiency in Deficiency::ALL.iter() {
+                let d: f64 = min_distance(&gradient, *deficiency);
+                assert!(d > 0.05, "{} with {}: samples only {:.3} apart", gradient.name, deficiency.name(), d);
+            }
+        }
+    }
+
+    #[test]
+    fn hue_rotation_is_not_safe() {
+        let hsv: Gradient = Gradient::new("hsv", vec![
+            ColorStop::new(0.0, 255, 0, 0), ColorStop::new(0.2, 255, 255, 0), ColorStop::new(0.4, 0, 255, 0),
+            ColorStop::new(0.6, 0, 255, 255), ColorStop::new(0.8, 0, 0, 255), ColorStop::new(1.0, 255, 0, 255),
+        ]);
+        assert!(min_distance(&hsv, Deficiency::Protanopia) < 0.05);
+    }
+
+    #[test]
+    fn normal_vision_is_unchanged() {
+        let c: LinearRgb = LinearRgb::new(0.2, 0.5, 0.8);
+        assert_eq!(Deficiency::Normal.simulate(c), c);
+    }
+
+    #[test]
+    fn gray_stays_gray() {
+        for deficiency in Deficiency::ALL.iter() {
+            let c: LinearRgb = deficiency.simulate(LinearRgb::new(0.5, 0.5, 0.5));
+            assert!((c.r - 0.5).abs() < 1e-3 && (c.g - 0.5).abs() < 1e-3 && (c.b - 0.5).abs() < 1e-3);
+        }
+    }
+}
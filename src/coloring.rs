@@ -0,0 +1,567 @@
+use std::sync::Arc;
+
+use yaml_rust::{Yaml, yaml::Hash};
+
+use crate::{dither::Dithering, color_vision::Deficiency, post::PostSettings, lighting::Light, gradient::Gradient, math::color::{Rgb, LinearRgb, Hsv}};
+
+const PALETTE_TABLE_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorMode {
+    Iteration,
+    Histogram,
+    Grayscale(GrayscaleCurve),
+}
+
+// how the grayscale mode maps the iteration count to a brightness
+#[derive(Clone, Copy, PartialEq)]
+pub enum GrayscaleCurve {
+    Linear,
+    Log,
+    Inverted,
+    Sepia,
+}
+
+// how a layer is combined with the layers below it, the formulas are the ones from css compositing
+#[derive(Clone, Copy, PartialEq)]
+pub enum BlendMode {
+    Normal,
+    Multiply,
+    Screen,
+    Overlay,
+    SoftLight,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum TransferFunction {
+    Linear,
+    Sqrt,
+    CubeRoot,
+    Log,
+    LogLog,
+}
+
+// maps a smooth iteration count to a palette position, one unit of position is one pass through the palette:
+// position = function(n) * density + offset
+#[derive(Clone, Copy)]
+pub struct Transfer {
+    pub function: TransferFunction,
+    pub density: f64,
+    pub offset: f64,
+}
+
+// Grid and Adaptive take the number of samples per side of a pixel, Adaptive only supersamples
+// pixels that differ strongly from their neighbors
+#[derive(Clone, Copy, PartialEq)]
+pub enum Antialiasing {
+    Off,
+    Grid(u32),
+    Adaptive(u32),
+}
+
+// one step of the coloring pipeline, opacity goes from 0 to 1
+#[derive(Clone)]
+pub struct Layer {
+    pub mode: ColorMode,
+    // None keeps the hsv hue rotation driven by value and saturation
+    pub gradient: Option<Gradient>,
+    pub transfer: Transfer,
+    pub opacity: f64,
+    pub blend: BlendMode,
+}
+
+#[derive(Clone)]
+pub struct ColorSettings {
+    pub value: f32,
+    pub saturation: f32,
+    // drawn bottom to top
+    pub layers: Vec<Layer>,
+    pub light: Light,
+    pub antialiasing: Antialiasing,
+    pub dithering: Dithering,
+    // preview only, the float frame and exports other than 8 bit png stay unchanged
+    pub deficiency: Deficiency,
+    pub post: PostSettings,
+}
+
+// turns iteration counts into colors, built once per recolor
+pub struct Colorizer {
+    settings: ColorSettings,
+    // one lookup table per layer
+    tables: Vec<Vec<Rgb>>,
+    heights: Arc<Vec<f64>>,
+    histogram: Arc<Histogram>,
+    width: i32,
+    height: i32,
+    max_iter: f64,
+}
+
+// cumulative distribution of the smooth iteration counts of one frame
+pub struct Histogram {
+    min: f64,
+    max: f64,
+    cdf: Vec<f64>,
+}
+
+impl Histogram {
+    pub fn new(counts: &[f64], bins: usize) -> Self {
+        let min: f64 = counts.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max: f64 = counts.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mut histogram: Histogram = Histogram { min, max, cdf: vec![0.0; bins] };
+        if counts.is_empty() || max <= min {
+            return histogram;
+        }
+        for n in counts {
+            let bin: usize = histogram.bin(*n);
+            histogram.cdf[bin] += 1.0;
+        }
+        let mut total: f64 = 0.0;
+        for bin in histogram.cdf.iter_mut() {
+            total += *bin;
+            *bin = total;
+        }
+        histogram.cdf.iter_mut().for_each(|bin| *bin /= total);
+        return histogram;
+    }
+
+    fn bin(&self, n: f64) -> usize {
+        let t: f64 = (n - self.min) / (self.max - self.min);
+        return ((t * self.cdf.len() as f64) as usize).min(self.cdf.len() - 1);
+    }
+
+    // fraction of the frame with a lower iteration count than n, from 0 to 1
+    pub fn cdf(&self, n: f64) -> f64 {
+        if self.max <= self.min {
+            return 0.0;
+        }
+        return self.cdf[self.bin(n)];
+    }
+}
+
+impl TransferFunction {
+    pub const ALL: [TransferFunction; 5] = [TransferFunction::Linear, TransferFunction::Sqrt, TransferFunction::CubeRoot, TransferFunction::Log, TransferFunction::LogLog];
+
+    pub fn apply(&self, n: f64) -> f64 {
+        let n: f64 = n.max(0.0);
+        match self {
+            TransferFunction::Linear => n,
+            TransferFunction::Sqrt => n.sqrt(),
+            TransferFunction::CubeRoot => n.cbrt(),
+            TransferFunction::Log => n.ln_1p(),
+            TransferFunction::LogLog => n.ln_1p().ln_1p(),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TransferFunction::Linear => "linear",
+            TransferFunction::Sqrt => "sqrt",
+            TransferFunction::CubeRoot => "cube root",
+            TransferFunction::Log => "log",
+            TransferFunction::LogLog => "log-log",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return TransferFunction::ALL.iter().find(|function| function.name() == name).copied();
+    }
+
+    pub fn next(&self) -> Self {
+        let index: usize = TransferFunction::ALL.iter().position(|function| function == self).unwrap();
+        return TransferFunction::ALL[(index + 1) % TransferFunction::ALL.len()];
+    }
+}
+
+impl Transfer {
+    pub fn new(function: TransferFunction, density: f64, offset: f64) -> Self {
+        return Transfer { function, density, offset };
+    }
+
+    pub fn apply(&self, n: f64) -> f64 {
+        return self.position(self.function.apply(n));
+    }
+
+    // density and offset without the transfer function, for mappings that already produce a position
+    pub fn position(&self, t: f64) -> f64 {
+        return t * self.density + self.offset;
+    }
+
+    pub fn to_yaml(&self) -> Yaml {
+        let mut hash: Hash = Hash::new();
+        hash.insert(Yaml::String("function".to_string()), Yaml::String(self.function.name().to_string()));
+        hash.insert(Yaml::String("density".to_string()), Yaml::Real(self.density.to_string()));
+        hash.insert(Yaml::String("offset".to_string()), Yaml::Real(self.offset.to_string()));
+        return Yaml::Hash(hash);
+    }
+
+    pub fn from_yaml(yaml: &Yaml) -> Option<Self> {
+        let function: TransferFunction = TransferFunction::from_name(yaml["function"].as_str()?)?;
+        let number = |value: &Yaml| -> Option<f64> { value.as_f64().or(value.as_i64().map(|v| v as f64)) };
+        return Some(Transfer { function, density: number(&yaml["density"])?, offset: number(&yaml["offset"])? });
+    }
+}
+
+impl BlendMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlendMode::Normal => "normal",
+            BlendMode::Multiply => "multiply",
+            BlendMode::Screen => "screen",
+            BlendMode::Overlay => "overlay",
+            BlendMode::SoftLight => "soft light",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return [BlendMode::Normal, BlendMode::Multiply, BlendMode::Screen, BlendMode::Overlay, BlendMode::SoftLight].iter().copied().find(|b| b.name() == name);
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            BlendMode::Normal => BlendMode::Multiply,
+            BlendMode::Multiply => BlendMode::Screen,
+            BlendMode::Screen => BlendMode::Overlay,
+            BlendMode::Overlay => BlendMode::SoftLight,
+            BlendMode::SoftLight => BlendMode::Normal,
+        }
+    }
+
+    // b is the backdrop and s the layer color, both channels from 0 to 1
+    pub fn blend(&self, b: f64, s: f64) -> f64 {
+        match self {
+            BlendMode::Normal => s,
+            BlendMode::Multiply => b * s,
+            BlendMode::Screen => b + s - b * s,
+            BlendMode::Overlay => if b <= 0.5 { 2.0 * b * s } else { BlendMode::Screen.blend(2.0 * b - 1.0, s) },
+            BlendMode::SoftLight => {
+                if s <= 0.5 {
+                    b - (1.0 - 2.0 * s) * b * (1.0 - b)
+                } else {
+                    let d: f64 = if b <= 0.25 { ((16.0 * b - 12.0) * b + 4.0) * b } else { b.sqrt() };
+                    b + (2.0 * s - 1.0) * (d - b)
+                }
+            }
+        }
+    }
+}
+
+impl GrayscaleCurve {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GrayscaleCurve::Linear => "linear",
+            GrayscaleCurve::Log => "log",
+            GrayscaleCurve::Inverted => "inverted",
+            GrayscaleCurve::Sepia => "sepia",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return [GrayscaleCurve::Linear, GrayscaleCurve::Log, GrayscaleCurve::Inverted, GrayscaleCurve::Sepia].iter().copied().find(|c| c.name() == name);
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            GrayscaleCurve::Linear => GrayscaleCurve::Log,
+            GrayscaleCurve::Log => GrayscaleCurve::Inverted,
+            GrayscaleCurve::Inverted => GrayscaleCurve::Sepia,
+            GrayscaleCurve::Sepia => GrayscaleCurve::Linear,
+        }
+    }
+
+    // color of the iteration count n, the interior is white except for inverted
+    pub fn color(&self, n: f64, max_iter: f64) -> Rgb {
+        let t: f64 = (n / max_iter).clamp(0.0, 1.0);
+        let level: f64 = match self {
+            GrayscaleCurve::Log => n.max(0.0).ln_1p() / max_iter.ln_1p(),
+            GrayscaleCurve::Inverted => 1.0 - t,
+            GrayscaleCurve::Linear | GrayscaleCurve::Sepia => t,
+        };
+        if *self == GrayscaleCurve::Sepia {
+            // duotone from a dark brown to a warm white, mixed in linear light like the gradients
+            let dark: LinearRgb = LinearRgb::from(Rgb::from_u8(38, 22, 10));
+            let light: LinearRgb = LinearRgb::from(Rgb::from_u8(255, 240, 205));
+            return Rgb::from(dark.lerp(light, level)).clamped();
+        }
+        return Rgb::new(level, level, level);
+    }
+}
+
+impl Layer {
+    pub fn new(mode: ColorMode, gradient: Option<Gradient>, transfer: Transfer) -> Self {
+        return Layer { mode, gradient, transfer, opacity: 1.0, blend: BlendMode::Normal };
+    }
+}
+
+impl Antialiasing {
+    pub fn name(&self) -> String {
+        match self {
+            Antialiasing::Off => "off".to_string(),
+            Antialiasing::Grid(n) => format!("{}x{} grid", n, n),
+            Antialiasing::Adaptive(n) => format!("{}x{} adaptive", n, n),
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Antialiasing::Off => Antialiasing::Adaptive(3),
+            Antialiasing::Adaptive(n) if *n < 4 => Antialiasing::Adaptive(n + 1),
+            Antialiasing::Adaptive(_) => Antialiasing::Grid(2),
+            Antialiasing::Grid(n) if *n < 4 => Antialiasing::Grid(n + 1),
+            Antialiasing::Grid(_) => Antialiasing::Off,
+        }
+    }
+}
+
+// hsv hue rotation, the hue wraps around once per unit of palette position
+pub fn hue_color(position: f64, saturation: f32, value: f32) -> (u8, u8, u8) {
+    return hue_rgb(position, saturation, value).to_u8();
+}
+
+fn hue_rgb(position: f64, saturation: f32, value: f32) -> Rgb {
+    let hue: f64 = position.rem_euclid(1.0) * 360.0;
+    return Rgb::from(Hsv::new(hue, saturation as f64 / 100.0, value as f64 / 100.0));
+}
+
+impl Colorizer {
+    pub fn new(settings: ColorSettings, heights: Arc<Vec<f64>>, histogram: Arc<Histogram>, width: i32, height: i32, max_iter: i32) -> Self {
+        // every palette, including the hsv hue rotation, is looked up in a table
+        let tables: Vec<Vec<Rgb>> = settings.layers.iter().map(|layer| match &layer.gradient {
+            Some(gradient) => (0..PALETTE_TABLE_SIZE).map(|i| gradient.sample_rgb(i as f64 / (PALETTE_TABLE_SIZE - 1) as f64)).collect(),
+            None => (0..PALETTE_TABLE_SIZE).map(|i| hue_rgb(i as f64 / PALETTE_TABLE_SIZE as f64, settings.value, settings.saturation)).collect(),
+        }).collect();
+        return Colorizer { settings, tables, heights, histogram, width, height, max_iter: max_iter as f64 };
+    }
+
+    pub fn settings(&self) -> &ColorSettings {
+        return &self.settings;
+    }
+
+    // interpolates between neighboring table entries so the palette doesn't add 8 bit steps
+    fn palette(&self, layer: usize, position: f64) -> Rgb {
+        let table: &[Rgb] = &self.tables[layer];
+        let (index, next, f) = match &self.settings.layers[layer].gradient {
+            Some(gradient) => {
+                let t: f64 = gradient.wrap(position) * (PALETTE_TABLE_SIZE - 1) as f64;
+                let index: usize = (t as usize).min(PALETTE_TABLE_SIZE - 1);
+                (index, (index + 1).min(PALETTE_TABLE_SIZE - 1), t - index as f64)
+            }
+            None => {
+                let t: f64 = position.rem_euclid(1.0) * PALETTE_TABLE_SIZE as f64;
+                let index: usize = t as usize % PALETTE_TABLE_SIZE;
+                (index, (index + 1) % PALETTE_TABLE_SIZE, t.fract())
+            }
+        };
+        let (a, b) = (table[index], table[next]);
+        return Rgb { r: a.r + (b.r - a.r) * f, g: a.g + (b.g - a.g) * f, b: a.b + (b.b - a.b) * f };
+    }
+
+    fn layer_color(&self, layer: usize, n: f64) -> Rgb {
+        let settings: &Layer = &self.settings.layers[layer];
+        match settings.mode {
+            ColorMode::Grayscale(curve) => curve.color(n, self.max_iter),
+            _ if n >= self.max_iter => Rgb::new(0.0, 0.0, 0.0),
+            ColorMode::Histogram => self.palette(layer, settings.transfer.position(self.histogram.cdf(n))),
+            ColorMode::Iteration => self.palette(layer, settings.transfer.apply(n)),
+        }
+    }
+
+    // color of the iteration count n in linear light, not yet rounded to 8 bits,
+    // pixel is the row major index the lighting is taken from
+    pub fn color(&self, n: f64, pixel: usize) -> [f32; 4] {
+        // composite the layers bottom to top, the stack is drawn over black at the end so the frame
+        // doesn't depend on what the window is cleared to and exports the way it looks
+        let mut backdrop: [f64; 3] = [0.0; 3];
+        let mut backdrop_alpha: f64 = 0.0;
+        for (i, layer) in self.settings.layers.iter().enumerate() {
+            let color: Rgb = self.layer_color(i, n);
+            let alpha: f64 = layer.opacity.clamp(0.0, 1.0);
+            if alpha <= 0.0 {
+                continue;
+            }
+            let source: [f64; 3] = [color.r, color.g, color.b];
+            let out_alpha: f64 = alpha + backdrop_alpha * (1.0 - alpha);
+            for c in 0..3 {
+                // where there is nothing below, the layer shows unblended
+                let blended: f64 = (1.0 - backdrop_alpha) * source[c] + backdrop_alpha * layer.blend.blend(backdrop[c], source[c]);
+                backdrop[c] = (alpha * blended + backdrop_alpha * (1.0 - alpha) * backdrop[c]) / out_alpha;
+            }
+            backdrop_alpha = out_alpha;
+        }
+        let mut color: Rgb = Rgb::new(backdrop[0] * backdrop_alpha, backdrop[1] * backdrop_alpha, backdrop[2] * backdrop_alpha);
+        let light: &Light = &self.settings.light;
+        if light.enabled && n < self.max_iter {
+            let (diffuse, specular) = light.shade(&self.heights, self.width, self.height, pixel as i32 % self.width, pixel as i32 / self.width);
+            color = Rgb::new(color.r * diffuse + specular, color.g * diffuse + specular, color.b * diffuse + specular);
+        }
+        let linear: LinearRgb = LinearRgb::from(color);
+        return [linear.r as f32, linear.g as f32, linear.b as f32, 1.0];
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yaml_rust::{YamlEmitter, YamlLoader};
+
+    use super::*;
+    use crate::math::color;
+
+    // smooth counts of escaped pixels only, clustered like a real frame
+    fn counts() -> Vec<f64> {
+        return (0..1000).map(|i| 2.0 + (i as f64 * 0.37).sin().abs() * (i % 17) as f64 * 3.5).collect();
+    }
+
+    #[test]
+    fn cdf_is_monotonic() {
+        let histogram: Histogram = Histogram::new(&counts(), 256);
+        let mut last: f64 = 0.0;
+        for i in 0..=1000 {
+            let c: f64 = histogram.cdf(2.0 + i as f64 * 0.06);
+            assert!(c >= last && (0.0..=1.0).contains(&c), "{} after {}", c, last);
+            last = c;
+        }
+    }
+
+    #[test]
+    fn last_bin_is_one() {
+        let counts: Vec<f64> = counts();
+        let max: f64 = counts.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let histogram: Histogram = Histogram::new(&counts, 256);
+        assert_eq!(histogram.cdf(max), 1.0);
+        assert_eq!(histogram.cdf(max + 100.0), 1.0);
+    }
+
+    #[test]
+    fn cdf_follows_the_distribution() {
+        // three quarters of the frame at the low end
+        let counts: Vec<f64> = (0..100).map(|i| if i < 75 { 1.0 } else { 10.0 }).collect();
+        let histogram: Histogram = Histogram::new(&counts, 16);
+        assert!((histogram.cdf(1.0) - 0.75).abs() < 1e-12);
+        assert!((histogram.cdf(5.0) - 0.75).abs() < 1e-12);
+    }
+
+    #[test]
+    fn frame_without_escapes() {
+        // every pixel inside the set leaves nothing to count
+        assert_eq!(Histogram::new(&[], 16).cdf(5.0), 0.0);
+        // and a flat frame has no spread to equalize
+        assert_eq!(Histogram::new(&[3.0; 10], 16).cdf(3.0), 0.0);
+    }
+
+    fn close(a: f64, b: f64) -> bool {
+        return (a - b).abs() < 1e-12;
+    }
+
+    #[test]
+    fn blend_modes() {
+        let cases: [(BlendMode, f64, f64, f64); 10] = [
+            (BlendMode::Normal, 0.3, 0.6, 0.6),
+            (BlendMode::Multiply, 0.5, 0.4, 0.2),
+            (BlendMode::Screen, 0.5, 0.4, 0.7),
+            // overlay multiplies dark backdrops and screens light ones
+            (BlendMode::Overlay, 0.25, 0.6, 0.3),
+            (BlendMode::Overlay, 0.75, 0.6, 0.8),
+            // soft light darkens with a dark layer and lightens with a light one
+            (BlendMode::SoftLight, 0.5, 0.25, 0.375),
+            (BlendMode::SoftLight, 0.64, 0.75, 0.72),
+            // below a quarter the backdrop goes through the polynomial instead of the square root
+            (BlendMode::SoftLight, 0.25, 1.0, 0.5),
+            (BlendMode::SoftLight, 0.125, 0.75, 0.234375),
+            (BlendMode::SoftLight, 0.3, 0.5, 0.3),
+        ];
+        for (mode, b, s, expected) in cases.iter() {
+            let blended: f64 = mode.blend(*b, *s);
+            assert!(close(blended, *expected), "{} of {} and {}: {} instead of {}", mode.name(), b, s, blended, expected);
+        }
+        // black and white stay put
+        for mode in [BlendMode::Multiply, BlendMode::Screen, BlendMode::Overlay, BlendMode::SoftLight].iter() {
+            assert!(close(mode.blend(0.0, 0.0), 0.0) && close(mode.blend(1.0, 1.0), 1.0), "{}", mode.name());
+        }
+    }
+
+    // a gray bottom layer of a quarter under an inverted one of three quarters, at n = 25 of 100
+    fn composite(opacities: (f64, f64), blend: BlendMode) -> f32 {
+        let mut bottom: Layer = Layer::new(ColorMode::Grayscale(GrayscaleCurve::Linear), None, Transfer::new(TransferFunction::Linear, 1.0, 0.0));
+        let mut top: Layer = Layer::new(ColorMode::Grayscale(GrayscaleCurve::Inverted), None, Transfer::new(TransferFunction::Linear, 1.0, 0.0));
+        bottom.opacity = opacities.0;
+        top.opacity = opacities.1;
+        top.blend = blend;
+        let settings: ColorSettings = ColorSettings { value: 100.0, saturation: 100.0, layers: vec![bottom, top], light: Light::new(0.0, 0.5, 0.0), antialiasing: Antialiasing::Off, dithering: Dithering::Off, deficiency: Deficiency::Normal, post: PostSettings::none() };
+        let colorizer: Colorizer = Colorizer::new(settings, Arc::new(vec![0.0]), Arc::new(Histogram::new(&[], 16)), 1, 1, 100);
+        let color: [f32; 4] = colorizer.color(25.0, 0);
+        assert!(color[0] == color[1] && color[1] == color[2] && color[3] == 1.0);
+        return color[0];
+    }
+
+    #[test]
+    fn layers_composite_by_opacity() {
+        let gray = |v: f64| -> f32 { color::srgb_to_linear(v) as f32 };
+        for (opacity, normal, multiply) in [(0.0, 0.25, 0.25), (0.5, 0.5, 0.21875), (1.0, 0.75, 0.1875)].iter() {
+            assert!((composite((1.0, *opacity), BlendMode::Normal) - gray(*normal)).abs() < 1e-6, "normal at {}", opacity);
+            assert!((composite((1.0, *opacity), BlendMode::Multiply) - gray(*multiply)).abs() < 1e-6, "multiply at {}", opacity);
+        }
+        // over nothing a layer shows unblended, and the stack is drawn over black
+        assert!((composite((0.0, 1.0), BlendMode::Multiply) - gray(0.75)).abs() < 1e-6);
+        assert!((composite((0.5, 0.0), BlendMode::Normal) - gray(0.125)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn grayscale_curves_are_gray() {
+        let max_iter: f64 = 80.0;
+        for (curve, at_zero, at_max) in [(GrayscaleCurve::Linear, 0.0, 1.0), (GrayscaleCurve::Log, 0.0, 1.0), (GrayscaleCurve::Inverted, 1.0, 0.0)].iter() {
+            for (n, level) in [(0.0, *at_zero), (max_iter, *at_max)].iter() {
+                let gray: Rgb = curve.color(*n, max_iter);
+                assert!(gray.r == gray.g && gray.g == gray.b, "{} at {}", curve.name(), n);
+                assert!(close(gray.r, *level), "{} at {}: {}", curve.name(), n, gray.r);
+            }
+            // and it stays gray in between
+            let gray: Rgb = curve.color(max_iter / 3.0, max_iter);
+            assert!(gray.r == gray.g && gray.g == gray.b && gray.r > 0.0 && gray.r < 1.0, "{}", curve.name());
+        }
+    }
+
+    #[test]
+    fn sepia_ends_at_the_duotone() {
+        assert_eq!(GrayscaleCurve::Sepia.color(0.0, 80.0).to_u8(), (38, 22, 10));
+        assert_eq!(GrayscaleCurve::Sepia.color(80.0, 80.0).to_u8(), (255, 240, 205));
+        // beyond the ends is clamped
+        assert_eq!(GrayscaleCurve::Sepia.color(-5.0, 80.0).to_u8(), (38, 22, 10));
+        assert_eq!(GrayscaleCurve::Sepia.color(200.0, 80.0).to_u8(), (255, 240, 205));
+    }
+
+    #[test]
+    fn transfer_functions_at_zero_and_max_iter() {
+        let max_iter: f64 = 1000.0;
+        let expected: [f64; 5] = [1000.0, 1000.0f64.sqrt(), 10.0, 1001.0f64.ln(), 1001.0f64.ln().ln_1p()];
+        for (function, at_max) in TransferFunction::ALL.iter().zip(expected.iter()) {
+            assert_eq!(function.apply(0.0), 0.0, "{}", function.name());
+            assert!((function.apply(max_iter) - at_max).abs() < 1e-9, "{}", function.name());
+            // counts below zero don't make a nan
+            assert_eq!(function.apply(-3.0), 0.0, "{}", function.name());
+        }
+    }
+
+    #[test]
+    fn density_and_offset() {
+        let transfer: Transfer = Transfer::new(TransferFunction::Sqrt, 0.5, 0.25);
+        assert_eq!(transfer.apply(0.0), 0.25);
+        assert_eq!(transfer.apply(16.0), 2.25);
+        assert_eq!(transfer.position(2.0), 1.25);
+    }
+
+    #[test]
+    fn transfer_yaml_round_trip() {
+        for (i, function) in TransferFunction::ALL.iter().enumerate() {
+            // whole numbers come back as integers
+            let transfer: Transfer = Transfer::new(*function, [2.0, 0.015, 1e-7, 3.5, 1.0][i], [0.0, 0.3, -1.25, 7.0, 0.5][i]);
+            let mut text: String = String::new();
+            YamlEmitter::new(&mut text).dump(&transfer.to_yaml()).unwrap();
+            let loaded: Transfer = Transfer::from_yaml(&YamlLoader::load_from_str(&text).unwrap()[0]).unwrap();
+            assert!(loaded.function == transfer.function, "{}", text);
+            assert_eq!((loaded.density, loaded.offset), (transfer.density, transfer.offset), "{}", text);
+        }
+        let broken: Vec<Yaml> = YamlLoader::load_from_str("function: cubic\ndensity: 1\noffset: 0").unwrap();
+        assert!(Transfer::from_yaml(&broken[0]).is_none());
+        let missing: Vec<Yaml> = YamlLoader::load_from_str("function: log\ndensity: 1").unwrap();
+        assert!(Transfer::from_yaml(&missing[0]).is_none());
+    }
+}
@@ -1,14 +1,11 @@
-use sfml::graphics::Color;
+use std::{collections::HashMap, fmt, fs, path::Path};
 
+use sfml::graphics::Color;
+use yaml_rust::{Yaml, YamlLoader, parser::{Parser, Event, MarkedEventReceiver}, scanner::Marker};
 
-pub struct RgbColor{
-    r: u8,
-    g: u8,
-    b: u8,
-}
+const ROLES: [&str; 5] = ["outline", "fill", "text", "hover", "selected"];
 
 // color enums
-#[allow(dead_code)]
 pub enum ColorSchemeNames {
     Outline,
     Fill,
@@ -17,38 +14,355 @@ pub enum ColorSchemeNames {
     Selected,
 }
 
+#[derive(Clone)]
 pub struct Colorscheme {
-    outline: RgbColor,
-    fill: RgbColor,
-    text: RgbColor,
-    hover: RgbColor,
-    selected: RgbColor,
+    pub name: String,
+    outline: Color,
+    fill: Color,
+    text: Color,
+    hover: Color,
+    selected: Color,
+    // scales the alpha of every color
     opaqueness: u8,
 }
 
-impl RgbColor {
-    pub fn new(r: u8, b: u8, g: u8) -> Self {
-        return RgbColor { r, g, b }
+// line is 1 based, 0 if the error isn't tied to a line
+#[derive(Debug)]
+pub struct ThemeError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl ThemeError {
+    fn new(line: usize, message: String) -> Self {
+        return ThemeError { line, message };
+    }
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.line == 0 {
+            return write!(f, "{}", self.message);
+        }
+        return write!(f, "line {}: {}", self.line, self.message);
     }
 }
 
 impl Colorscheme {
-    pub fn new(_scheme: &str, opaqueness: u8) -> Self {
-        let outline: RgbColor = RgbColor::new(33, 31, 32);
-        let fill: RgbColor = RgbColor::new(79, 75, 77);
-        let text: RgbColor = RgbColor::new(255, 255, 255);
-        let hover: RgbColor = RgbColor::new(28, 13, 20);
-        let selected: RgbColor = RgbColor::new(12, 5, 9);
-        return Colorscheme { outline, fill, text, hover, selected, opaqueness };
+    // the built in scheme, used until a theme file is loaded
+    pub fn new(scheme: &str, opaqueness: u8) -> Self {
+        let outline: Color = Color::rgb(33, 32, 31);
+        let fill: Color = Color::rgb(79, 77, 75);
+        let text: Color = Color::rgb(255, 255, 255);
+        let hover: Color = Color::rgb(28, 20, 13);
+        let selected: Color = Color::rgb(12, 9, 5);
+        return Colorscheme { name: scheme.to_string(), outline, fill, text, hover, selected, opaqueness };
     }
 
     pub fn sfml_color(&self, color: ColorSchemeNames) -> Color {
-        match color {
-            ColorSchemeNames::Outline => Color::rgba(self.outline.r, self.outline.g, self.outline.b, self.opaqueness),
-            ColorSchemeNames::Fill => Color::rgba(self.fill.r, self.fill.g, self.fill.b, self.opaqueness),
-            ColorSchemeNames::Text => Color::rgba(self.text.r, self.text.g, self.text.b, self.opaqueness),
-            ColorSchemeNames::Hover => Color::rgba(self.hover.r, self.hover.g, self.hover.b, self.opaqueness),
-            ColorSchemeNames::Selected => Color::rgba(self.selected.r, self.selected.g, self.selected.b, self.opaqueness),
+        let c: Color = match color {
+            ColorSchemeNames::Outline => self.outline,
+            ColorSchemeNames::Fill => self.fill,
+            ColorSchemeNames::Text => self.text,
+            ColorSchemeNames::Hover => self.hover,
+            ColorSchemeNames::Selected => self.selected,
+        };
+        return Color::rgba(c.r, c.g, c.b, (c.a as u32 * self.opaqueness as u32 / 255) as u8);
+    }
+}
+
+// a theme file maps scheme names to schemes:
+//   name:
+//     outline, fill, text, hover, selected: "#rrggbb", "#rrggbbaa", [r, g, b], [r, g, b, a] or {r, g, b, a}
+//     opacity: 0 to 255 or 0.0 to 1.0, optional
+pub fn load<P>(filename: P) -> Result<Vec<Colorscheme>, ThemeError>
+where P: AsRef<Path>, {
+    // a file that can't be read (or isn't utf-8) fails as a whole
+    let contents: String = match fs::read_to_string(filename) {
+        Ok(x) => x,
+        Err(e) => return Err(ThemeError::new(0, e.to_string())),
+    };
+    return parse(&contents);
+}
+
+// the lines of the scheme names and of the keys inside each scheme, yaml-rust drops markers when loading
+#[derive(Default)]
+struct SchemeLines {
+    depth: usize,
+    scheme: Option<String>,
+    expect_scheme: bool,
+    expect_key: bool,
+    done: bool,
+    schemes: HashMap<String, usize>,
+    keys: HashMap<(String, String), usize>,
+}
+
+impl SchemeLines {
+    fn new(contents: &str) -> Self {
+        let mut lines: SchemeLines = SchemeLines { expect_scheme: true, ..SchemeLines::default() };
+        // only called once YamlLoader has read the contents, so there is no error left to report
+        let _ = Parser::new(contents.chars()).load(&mut lines, false);
+        return lines;
+    }
+
+    fn scheme(&self, scheme: &str) -> usize {
+        return self.schemes.get(scheme).copied().unwrap_or(0);
+    }
+
+    fn key(&self, scheme: &str, key: &str) -> usize {
+        return self.keys.get(&(scheme.to_string(), key.to_string())).copied().unwrap_or_else(|| self.scheme(scheme));
+    }
+}
+
+impl MarkedEventReceiver for SchemeLines {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.done {
+            return;
+        }
+        let node: bool = matches!(event, Event::Scalar(..) | Event::SequenceStart(..) | Event::MappingStart(..) | Event::Alias(..));
+        if node && self.depth == 1 {
+            if self.expect_scheme {
+                self.scheme = match &event {
+                    Event::Scalar(name, ..) => {
+                        self.schemes.insert(name.clone(), mark.line());
+                        Some(name.clone())
+                    }
+                    _ => None,
+                };
+            }
+            self.expect_scheme = !self.expect_scheme;
+            self.expect_key = true;
+        }
+        if node && self.depth == 2 {
+            if let (true, Some(scheme), Event::Scalar(key, ..)) = (self.expect_key, &self.scheme, &event) {
+                self.keys.insert((scheme.clone(), key.clone()), mark.line());
+            }
+            self.expect_key = !self.expect_key;
+        }
+        match event {
+            Event::SequenceStart(..) | Event::MappingStart(..) => self.depth += 1,
+            Event::SequenceEnd | Event::MappingEnd => self.depth -= 1,
+            Event::DocumentEnd => self.done = true,
+            _ => (),
+        }
+    }
+}
+
+pub fn parse(contents: &str) -> Result<Vec<Colorscheme>, ThemeError> {
+    let docs: Vec<Yaml> = match YamlLoader::load_from_str(contents) {
+        Ok(x) => x,
+        Err(e) => return Err(ThemeError::new(e.marker().line(), e.to_string())),
+    };
+    let schemes = match docs.first().and_then(|doc| doc.as_hash()) {
+        Some(x) => x,
+        None => return Err(ThemeError::new(0, "expected a list of named schemes".to_string())),
+    };
+    let lines: SchemeLines = SchemeLines::new(contents);
+    let mut parsed: Vec<Colorscheme> = Vec::new();
+    for (name, scheme) in schemes {
+        let name: String = match name {
+            Yaml::String(s) => s.clone(),
+            Yaml::Integer(i) => i.to_string(),
+            _ => return Err(ThemeError::new(0, "scheme names must be text".to_string())),
+        };
+        match parse_scheme(&name, scheme) {
+            Ok(x) => parsed.push(x),
+            Err((key, e)) => {
+                let line: usize = key.map(|key| lines.key(&name, &key)).unwrap_or_else(|| lines.scheme(&name));
+                return Err(ThemeError::new(line, format!("scheme \"{}\": {}", name, e)));
+            }
         }
     }
-}
\ No newline at end of file
+    if parsed.is_empty() {
+        return Err(ThemeError::new(0, "no schemes in the file".to_string()));
+    }
+    return Ok(parsed);
+}
+
+// errors carry the key they came from, none when it's the scheme as a whole
+fn parse_scheme(name: &str, yaml: &Yaml) -> Result<Colorscheme, (Option<String>, String)> {
+    let hash = match yaml.as_hash() {
+        Some(x) => x,
+        None => return Err((None, "expected the colors of the scheme".to_string())),
+    };
+    let mut colors: [Option<Color>; 5] = [None; 5];
+    let mut opaqueness: u8 = 255;
+    for (key, value) in hash {
+        let key: &str = key.as_str().unwrap_or("");
+        if key == "opacity" {
+            opaqueness = parse_opacity(value).map_err(|e| (Some(key.to_string()), e))?;
+            continue;
+        }
+        match ROLES.iter().position(|role| *role == key) {
+            Some(i) => colors[i] = Some(parse_color(value).map_err(|e| (Some(key.to_string()), format!("{}: {}", key, e)))?),
+            None => return Err((Some(key.to_string()), format!("unknown key \"{}\", expected one of {} or opacity", key, ROLES.join(", ")))),
+        }
+    }
+    let mut missing = ROLES.iter().zip(colors.iter()).filter(|(_, c)| c.is_none()).map(|(role, _)| *role).peekable();
+    if missing.peek().is_some() {
+        return Err((None, format!("missing {}", missing.collect::<Vec<&str>>().join(", "))));
+    }
+    let [outline, fill, text, hover, selected] = colors.map(|c| c.unwrap());
+    return Ok(Colorscheme { name: name.to_string(), outline, fill, text, hover, selected, opaqueness });
+}
+
+fn parse_color(yaml: &Yaml) -> Result<Color, String> {
+    let components: Vec<u8> = match yaml {
+        Yaml::String(hex) => return parse_hex(hex),
+        Yaml::Array(values) => values.iter().map(parse_component).collect::<Result<Vec<u8>, String>>()?,
+        Yaml::Hash(_) => {
+            let mut components: Vec<u8> = Vec::with_capacity(4);
+            for key in ["r", "g", "b"].iter() {
+                match &yaml[*key] {
+                    Yaml::BadValue => return Err(format!("missing {}", key)),
+                    value => components.push(parse_component(value)?),
+                }
+            }
+            if !yaml["a"].is_badvalue() {
+                components.push(parse_component(&yaml["a"])?);
+            }
+            components
+        }
+        _ => return Err("expected \"#rrggbb\", [r, g, b] or {r, g, b}".to_string()),
+    };
+    match components.as_slice() {
+        [r, g, b] => Ok(Color::rgb(*r, *g, *b)),
+        [r, g, b, a] => Ok(Color::rgba(*r, *g, *b, *a)),
+        _ => Err(format!("expected 3 or 4 components, got {}", components.len())),
+    }
+}
+
+fn parse_component(yaml: &Yaml) -> Result<u8, String> {
+    match yaml {
+        Yaml::Integer(i) if (0..=255).contains(i) => Ok(*i as u8),
+        Yaml::Integer(i) => Err(format!("{} is out of range 0 to 255", i)),
+        _ => Err("color components must be whole numbers from 0 to 255".to_string()),
+    }
+}
+
+fn parse_hex(hex: &str) -> Result<Color, String> {
+    let digits: &str = hex.trim().trim_start_matches('#');
+    if (digits.len() != 6 && digits.len() != 8) || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!("\"{}\" is not a hex color, expected \"#rrggbb\" or \"#rrggbbaa\"", hex));
+    }
+    let byte = |i: usize| -> u8 { u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).unwrap() };
+    if digits.len() == 8 {
+        return Ok(Color::rgba(byte(0), byte(1), byte(2), byte(3)));
+    }
+    return Ok(Color::rgb(byte(0), byte(1), byte(2)));
+}
+
+fn parse_opacity(yaml: &Yaml) -> Result<u8, String> {
+    match yaml {
+        Yaml::Integer(i) if (0..=255).contains(i) => Ok(*i as u8),
+        Yaml::Real(_) => match yaml.as_f64() {
+            Some(f) if (0.0..=1.0).contains(&f) => Ok((f * 255.0).round() as u8),
+            _ => Err("opacity must be from 0.0 to 1.0 or from 0 to 255".to_string()),
+        },
+        _ => Err("opacity must be from 0.0 to 1.0 or from 0 to 255".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::PathBuf};
+
+    use super::*;
+
+    fn yaml(text: &str) -> Yaml {
+        return YamlLoader::load_from_str(text).unwrap().remove(0);
+    }
+
+    // the error, panics on success
+    fn error(result: Result<Vec<Colorscheme>, ThemeError>) -> ThemeError {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e,
+        }
+    }
+
+    const DARK: &str = "dark:\n  outline: \"#212020\"\n  fill: [79, 77, 75]\n  text: {r: 255, g: 255, b: 255}\n  hover: [28, 20, 13, 128]\n  selected: \"#0c090580\"\n";
+
+    #[test]
+    fn color_forms() {
+        assert_eq!(parse_color(&yaml("\"#ff8000\"")), Ok(Color::rgb(255, 128, 0)));
+        assert_eq!(parse_color(&yaml("\"#ff800040\"")), Ok(Color::rgba(255, 128, 0, 64)));
+        assert_eq!(parse_color(&yaml("[1, 2, 3]")), Ok(Color::rgb(1, 2, 3)));
+        assert_eq!(parse_color(&yaml("[1, 2, 3, 4]")), Ok(Color::rgba(1, 2, 3, 4)));
+        assert_eq!(parse_color(&yaml("{r: 1, g: 2, b: 3}")), Ok(Color::rgb(1, 2, 3)));
+        assert_eq!(parse_color(&yaml("{r: 1, g: 2, b: 3, a: 4}")), Ok(Color::rgba(1, 2, 3, 4)));
+    }
+
+    #[test]
+    fn bad_colors() {
+        assert!(parse_color(&yaml("\"#ff80\"")).is_err());
+        assert!(parse_color(&yaml("\"#gg8000\"")).is_err());
+        assert_eq!(parse_color(&yaml("[1, 2, 256]")), Err("256 is out of range 0 to 255".to_string()));
+        assert!(parse_color(&yaml("[1, 2, 0.5]")).is_err());
+        assert_eq!(parse_color(&yaml("[1, 2]")), Err("expected 3 or 4 components, got 2".to_string()));
+        assert_eq!(parse_color(&yaml("{r: 1, b: 3}")), Err("missing g".to_string()));
+        assert!(parse_color(&yaml("12")).is_err());
+    }
+
+    #[test]
+    fn opacity() {
+        assert_eq!(parse_opacity(&yaml("128")), Ok(128));
+        assert_eq!(parse_opacity(&yaml("0.5")), Ok(128));
+        assert_eq!(parse_opacity(&yaml("1.0")), Ok(255));
+        assert!(parse_opacity(&yaml("300")).is_err());
+        assert!(parse_opacity(&yaml("-1")).is_err());
+        assert!(parse_opacity(&yaml("1.5")).is_err());
+    }
+
+    #[test]
+    fn schemes() {
+        let schemes: Vec<Colorscheme> = match parse(&format!("{}  opacity: 0.5\nlight:\n  outline: \"#ffffff\"\n  fill: \"#ffffff\"\n  text: \"#000000\"\n  hover: \"#eeeeee\"\n  selected: \"#dddddd\"\n", DARK)) {
+            Ok(x) => x,
+            Err(e) => panic!("{}", e),
+        };
+        assert_eq!(schemes.iter().map(|s| s.name.as_str()).collect::<Vec<&str>>(), vec!["dark", "light"]);
+        let dark: &Colorscheme = &schemes[0];
+        assert_eq!(dark.sfml_color(ColorSchemeNames::Outline), Color::rgba(33, 32, 32, 128));
+        assert_eq!(dark.sfml_color(ColorSchemeNames::Text), Color::rgba(255, 255, 255, 128));
+        assert_eq!(dark.sfml_color(ColorSchemeNames::Hover), Color::rgba(28, 20, 13, 64));
+        assert_eq!(schemes[1].sfml_color(ColorSchemeNames::Text), Color::rgb(0, 0, 0));
+    }
+
+    #[test]
+    fn missing_role() {
+        let e: ThemeError = error(parse("dark:\n  outline: \"#212020\"\n  fill: [79, 77, 75]\n"));
+        assert_eq!(e.message, "scheme \"dark\": missing text, hover, selected");
+        assert_eq!(e.to_string(), "line 1: scheme \"dark\": missing text, hover, selected");
+    }
+
+    #[test]
+    fn error_lines() {
+        // the scheme name for a missing role, the key for anything wrong with one
+        assert_eq!(error(parse(&format!("{}plain:\n  fill: [1, 2, 3]\n", DARK))).line, 7);
+        let unknown: ThemeError = error(parse(&format!("{}  border: [1, 2, 3]\n", DARK)));
+        assert_eq!(unknown.line, 7);
+        assert!(unknown.message.contains("unknown key \"border\""));
+        assert_eq!(error(parse(&format!("{}  opacity: 300\n", DARK))).line, 7);
+        assert_eq!(error(parse("dark:\n  outline: \"#212020\"\n  fill: [79, 77, 999]\n")).line, 3);
+        // yaml syntax errors
+        assert_eq!(error(parse("dark:\n  outline: [1, 2, 3\n  fill: \"#000000\"\n")).line, 3);
+        assert_eq!(error(parse("dark:\n  fill: [1, 2, 3]]\n")).line, 2);
+    }
+
+    #[test]
+    fn not_schemes() {
+        assert!(parse("").is_err());
+        assert!(parse("- dark\n- light\n").is_err());
+        assert_eq!(error(parse("dark: 12\n")).line, 1);
+    }
+
+    #[test]
+    fn unreadable_files() {
+        assert!(load("themes/missing.yaml").is_err());
+        let path: PathBuf = env::temp_dir().join(format!("mandelbrot-test-{}-theme.yaml", std::process::id()));
+        fs::write(&path, b"dark:\n  outline: \"#21\xff\"\n").unwrap();
+        let result = load(&path);
+        let _ = fs::remove_file(&path);
+        assert_eq!(error(result).line, 0);
+    }
+}
@@ -0,0 +1,235 @@
+use std::sync::OnceLock;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::math::color;
+
+const BLUE_NOISE_SIZE: usize = 64;
+
+// how the float frame is rounded to 8 bits per channel
+#[derive(Clone, Copy, PartialEq)]
+pub enum Dithering {
+    Off,
+    Bayer,
+    BlueNoise,
+    FloydSteinberg,
+}
+
+impl Dithering {
+    pub const ALL: [Dithering; 4] = [Dithering::Off, Dithering::Bayer, Dithering::BlueNoise, Dithering::FloydSteinberg];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dithering::Off => "off",
+            Dithering::Bayer => "bayer",
+            Dithering::BlueNoise => "blue noise",
+            Dithering::FloydSteinberg => "floyd-steinberg",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Dithering::Off => Dithering::Bayer,
+            Dithering::Bayer => Dithering::BlueNoise,
+            Dithering::BlueNoise => Dithering::FloydSteinberg,
+            Dithering::FloydSteinberg => Dithering::Off,
+        }
+    }
+}
+
+// turns a row major frame in linear light into rgba bytes, the dithering works on the gamma encoded values
+pub fn quantize(frame: &[[f32; 4]], width: usize, dithering: Dithering) -> Vec<u8> {
+    let mut encoded: Vec<[f32; 4]> = frame.iter().map(|p| {
+        let c = |v: f32| -> f32 { (color::linear_to_srgb(v.clamp(0.0, 1.0) as f64) * 255.0) as f32 };
+        [c(p[0]), c(p[1]), c(p[2]), p[3].clamp(0.0, 1.0) * 255.0]
+    }).collect();
+    match dithering {
+        Dithering::Off => (),
+        Dithering::Bayer => add_threshold(&mut encoded, width, |x, y| bayer(x % 8, y % 8)),
+        Dithering::BlueNoise => {
+            let noise: &[f32] = blue_noise();
+            add_threshold(&mut encoded, width, |x, y| noise[(y % BLUE_NOISE_SIZE) * BLUE_NOISE_SIZE + x % BLUE_NOISE_SIZE]);
+        }
+        Dithering::FloydSteinberg => floyd_steinberg(&mut encoded, width),
+    }
+    let mut pixels: Vec<u8> = Vec::with_capacity(encoded.len() * 4);
+    for p in &encoded {
+        pixels.extend(p.iter().map(|v| v.round().clamp(0.0, 255.0) as u8));
+    }
+    return pixels;
+}
+
+// ordered dithering, threshold gives an offset from -0.5 to 0.5 of one step for a pixel
+fn add_threshold<F>(encoded: &mut [[f32; 4]], width: usize, threshold: F)
+where F: Fn(usize, usize) -> f32, {
+    for (i, p) in encoded.iter_mut().enumerate() {
+        let t: f32 = threshold(i % width, i / width);
+        (0..3).for_each(|c| p[c] += t);
+    }
+}
+
+// 8x8 bayer matrix, the bits of x ^ y and y interleaved in reverse order give the rank
+fn bayer(x: usize, y: usize) -> f32 {
+    let (mut x, mut y) = (x, y);
+    let mut rank: usize = 0;
+    for _ in 0..3 {
+        rank = (rank << 2) | ((x ^ y) & 1) << 1 | (y & 1);
+        x >>= 1;
+        y >>= 1;
+    }
+    return (rank as f32 + 0.5) / 64.0 - 0.5;
+}
+
+// serpentine scan so the error doesn't drift in one direction
+fn floyd_steinberg(encoded: &mut [[f32; 4]], width: usize) {
+    let height: usize = encoded.len() / width;
+    for y in 0..height {
+        let reverse: bool = y % 2 == 1;
+        for j in 0..width {
+            let x: usize = if reverse { width - 1 - j } else { j };
+            let i: usize = y * width + x;
+            for c in 0..3 {
+                let old: f32 = encoded[i][c];
+                let new: f32 = old.round().clamp(0.0, 255.0);
+                encoded[i][c] = new;
+                let error: f32 = old - new;
+                let ahead: Option<usize> = if reverse { x.checked_sub(1) } else { Some(x + 1).filter(|x| *x < width) };
+                let behind: Option<usize> = if reverse { Some(x + 1).filter(|x| *x < width) } else { x.checked_sub(1) };
+                if let Some(ahead) = ahead {
+                    encoded[i - x + ahead][c] += error * 7.0 / 16.0;
+                }
+                if y + 1 < height {
+                    let below: usize = i + width;
+                    if let Some(behind) = behind {
+                        encoded[below - x + behind][c] += error * 3.0 / 16.0;
+                    }
+                    encoded[below][c] += error * 5.0 / 16.0;
+                    if let Some(ahead) = ahead {
+                        encoded[below - x + ahead][c] += error * 1.0 / 16.0;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// generated on first use, the same for every run
+fn blue_noise() -> &'static [f32] {
+    static NOISE: OnceLock<Vec<f32>> = OnceLock::new();
+    return NOISE.get_or_init(void_and_cluster);
+}
+
+// void and cluster (ulichney 1993) on a tiling texture: every cell gets a rank so that the cells
+// below any threshold are spread evenly, returned as offsets from -0.5 to 0.5
+fn void_and_cluster() -> Vec<f32> {
+    let n: usize = BLUE_NOISE_SIZE;
+    let cells: usize = n * n;
+    let wrapped = |d: usize| -> f32 { d.min(n - d) as f32 };
+    let kernel: Vec<f32> = (0..cells).map(|i| {
+        let (dx, dy) = (wrapped(i % n), wrapped(i / n));
+        (-(dx * dx + dy * dy) / (2.0 * 1.5 * 1.5)).exp()
+    }).collect();
+    // energy is the sum of the kernel of every set cell, high in clusters and low in voids
+    let set = |pattern: &mut Vec<bool>, energy: &mut Vec<f32>, cell: usize, on: bool| {
+        pattern[cell] = on;
+        let sign: f32 = if on { 1.0 } else { -1.0 };
+        for (j, e) in energy.iter_mut().enumerate() {
+            let dx: usize = (j % n + n - cell % n) % n;
+            let dy: usize = (j / n + n - cell / n) % n;
+            *e += sign * kernel[dy * n + dx];
+        }
+    };
+    let tightest_cluster = |pattern: &[bool], energy: &[f32]| -> usize {
+        (0..cells).filter(|i| pattern[*i]).max_by(|a, b| energy[*a].total_cmp(&energy[*b])).unwrap()
+    };
+    let largest_void = |pattern: &[bool], energy: &[f32]| -> usize {
+        (0..cells).filter(|i| !pattern[*i]).min_by(|a, b| energy[*a].total_cmp(&energy[*b])).unwrap()
+    };
+
+    let mut pattern: Vec<bool> = vec![false; cells];
+    let mut energy: Vec<f32> = vec![0.0; cells];
+    let mut rng: StdRng = StdRng::seed_from_u64(1);
+    let ones: usize = cells / 10;
+    let mut count: usize = 0;
+    while count < ones {
+        let cell: usize = rng.gen_range(0..cells);
+        if !pattern[cell] {
+            set(&mut pattern, &mut energy, cell, true);
+            count += 1;
+        }
+    }
+    // move points from the tightest cluster to the largest void until the pattern is even
+    loop {
+        let cluster: usize = tightest_cluster(&pattern, &energy);
+        set(&mut pattern, &mut energy, cluster, false);
+        let void: usize = largest_void(&pattern, &energy);
+        set(&mut pattern, &mut energy, void, true);
+        if void == cluster {
+            break;
+        }
+    }
+
+    let mut rank: Vec<usize> = vec![0; cells];
+    // rank the initial points by taking clusters away from a copy
+    let (mut removed, mut removed_energy) = (pattern.clone(), energy.clone());
+    for r in (0..ones).rev() {
+        let cluster: usize = tightest_cluster(&removed, &removed_energy);
+        set(&mut removed, &mut removed_energy, cluster, false);
+        rank[cluster] = r;
+    }
+    // and the rest by filling voids
+    for r in ones..cells {
+        let void: usize = largest_void(&pattern, &energy);
+        set(&mut pattern, &mut energy, void, true);
+        rank[void] = r;
+    }
+    return rank.iter().map(|r| (*r as f32 + 0.5) / cells as f32 - 0.5).collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ranks(offsets: &[f32]) -> Vec<usize> {
+        let mut ranks: Vec<usize> = offsets.iter().map(|t| ((t + 0.5) * offsets.len() as f32 - 0.5).round() as usize).collect();
+        ranks.sort_unstable();
+        return ranks;
+    }
+
+    #[test]
+    fn bayer_ranks_are_a_permutation() {
+        let offsets: Vec<f32> = (0..64).map(|i| bayer(i % 8, i / 8)).collect();
+        assert_eq!(ranks(&offsets), (0..64).collect::<Vec<usize>>());
+        // the 2x2 corner is the classic 0 2 / 3 1 pattern
+        let corner: Vec<f32> = [(0, 0), (1, 0), (0, 1), (1, 1)].iter().map(|(x, y)| (bayer(*x, *y) + 0.5) * 64.0 - 0.5).collect();
+        assert_eq!(corner, vec![0.0, 32.0, 48.0, 16.0]);
+    }
+
+    #[test]
+    fn blue_noise_ranks_are_a_permutation() {
+        assert_eq!(ranks(blue_noise()), (0..BLUE_NOISE_SIZE * BLUE_NOISE_SIZE).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn floyd_steinberg_keeps_the_average() {
+        let (width, height) = (64, 64);
+        let mut encoded: Vec<[f32; 4]> = vec![[100.3, 17.5, 200.8, 255.0]; width * height];
+        floyd_steinberg(&mut encoded, width);
+        let expected: [f32; 3] = [100.3, 17.5, 200.8];
+        for c in 0..3 {
+            assert!(encoded.iter().all(|p| p[c] == p[c].round()));
+            let mean: f32 = encoded.iter().map(|p| p[c]).sum::<f32>() / encoded.len() as f32;
+            assert!((mean - expected[c]).abs() < 0.02, "channel {}: {} instead of {}", c, mean, expected[c]);
+        }
+    }
+
+    #[test]
+    fn black_and_white_stay_exact() {
+        for dithering in Dithering::ALL.iter() {
+            for (v, byte) in [(0.0, 0), (1.0, 255)].iter() {
+                let pixels: Vec<u8> = quantize(&vec![[*v, *v, *v, 1.0]; 16 * 16], 16, *dithering);
+                assert!(pixels.chunks(4).all(|p| p[..3].iter().all(|b| *b == *byte)), "{} moved {}", dithering.name(), v);
+            }
+        }
+    }
+}
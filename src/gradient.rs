@@ -0,0 +1,246 @@
+use crate::math::color::{Rgb, LinearRgb, Oklab};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Interpolation {
+    Oklab,
+    LinearRgb,
+}
+
+// what happens to palette positions outside of 0..1
+#[derive(Clone, Copy, PartialEq)]
+pub enum RepeatMode {
+    Repeat,
+    Mirror,
+}
+
+#[derive(Clone, Copy)]
+pub struct ColorStop {
+    pub position: f64,
+    pub color: (u8, u8, u8),
+}
+
+#[derive(Clone)]
+pub struct Gradient {
+    pub name: String,
+    stops: Vec<ColorStop>,
+    pub interpolation: Interpolation,
+    pub repeat: RepeatMode,
+}
+
+impl ColorStop {
+    pub fn new(position: f64, r: u8, g: u8, b: u8) -> Self {
+        return ColorStop { position, color: (r, g, b) };
+    }
+}
+
+impl Gradient {
+    pub fn new(name: &str, mut stops: Vec<ColorStop>) -> Self {
+        stops.sort_by(|a, b| a.position.total_cmp(&b.position));
+        return Gradient { name: name.to_string(), stops, interpolation: Interpolation::Oklab, repeat: RepeatMode::Repeat };
+    }
+
+    pub fn stops(&self) -> &[ColorStop] {
+        return &self.stops;
+    }
+
+    // folds any palette position into 0..1
+    pub fn wrap(&self, t: f64) -> f64 {
+        match self.repeat {
+            RepeatMode::Repeat => t.rem_euclid(1.0),
+            RepeatMode::Mirror => {
+                let t: f64 = t.rem_euclid(2.0);
+                if t > 1.0 { 2.0 - t } else { t }
+            }
+        }
+    }
+
+    pub fn sample(&self, t: f64) -> (u8, u8, u8) {
+        return self.sample_rgb(t).to_u8();
+    }
+
+    // without rounding to 8 bits
+    pub fn sample_rgb(&self, t: f64) -> Rgb {
+        let t: f64 = self.wrap(t);
+        let first: &ColorStop = match self.stops.first() {
+            Some(x) => x,
+            None => return Rgb::new(0.0, 0.0, 0.0),
+        };
+        if t <= first.position {
+            return Rgb::from_u8(first.color.0, first.color.1, first.color.2);
+        }
+        for pair in self.stops.windows(2) {
+            let (a, b) = (pair[0], pair[1]);
+            if t <= b.position {
+                let f: f64 = if b.position > a.position { (t - a.position) / (b.position - a.position) } else { 1.0 };
+                return self.interpolate(a.color, b.color, f);
+            }
+        }
+        let last: (u8, u8, u8) = self.stops[self.stops.len() - 1].color;
+        return Rgb::from_u8(last.0, last.1, last.2);
+    }
+
+    // sampling every pixel is slow, the coloring thread looks colors up in this table instead
+    // the last entry is palette position 1, a table of one entry only has the start
+    pub fn lookup_table(&self, size: usize) -> Vec<(u8, u8, u8)> {
+        let last: f64 = (size.max(2) - 1) as f64;
+        return (0..size).map(|i| self.sample(i as f64 / last)).collect();
+    }
+
+    fn interpolate(&self, a: (u8, u8, u8), b: (u8, u8, u8), f: f64) -> Rgb {
+        let a: LinearRgb = LinearRgb::from(Rgb::from_u8(a.0, a.1, a.2));
+        let b: LinearRgb = LinearRgb::from(Rgb::from_u8(b.0, b.1, b.2));
+        let mixed: LinearRgb = match self.interpolation {
+            Interpolation::LinearRgb => a.lerp(b, f),
+            Interpolation::Oklab => LinearRgb::from(Oklab::from(a).lerp(Oklab::from(b), f)),
+        };
+        return Rgb::from(mixed).clamped();
+    }
+
+    pub fn builtin() -> Vec<Gradient> {
+        let classic: [(u8, u8, u8); 16] = [
+            (66, 30, 15), (25, 7, 26), (9, 1, 47), (4, 4, 73),
+            (0, 7, 100), (12, 44, 138), (24, 82, 177), (57, 125, 209),
+            (134, 181, 229), (211, 236, 248), (241, 233, 191), (248, 201, 0),
+            (255, 170, 0), (204, 128, 0), (153, 87, 0), (106, 52, 3),
+        ];
+        let mut classic_stops: Vec<ColorStop> = classic.iter().enumerate().map(|(i, c)| ColorStop::new(i as f64 / 16.0, c.0, c.1, c.2)).collect();
+        // repeat the first color at the end so the palette wraps around without a seam
+        classic_stops.push(ColorStop::new(1.0, classic[0].0, classic[0].1, classic[0].2));
+
+        return vec![
+            Gradient::new("classic", classic_stops),
+            Gradient::new("ultra", vec![
+                ColorStop::new(0.0, 0, 7, 100),
+                ColorStop::new(0.16, 32, 107, 203),
+                ColorStop::new(0.42, 237, 255, 255),
+                ColorStop::new(0.6425, 255, 170, 0),
+                ColorStop::new(0.8575, 0, 2, 0),
+                ColorStop::new(1.0, 0, 7, 100),
+            ]),
+            Gradient::new("fire", vec![
+                ColorStop::new(0.0, 0, 0, 0),
+                ColorStop::new(0.3, 128, 0, 0),
+                ColorStop::new(0.55, 255, 80, 0),
+                ColorStop::new(0.8, 255, 200, 0),
+                ColorStop::new(1.0, 255, 255, 255),
+            ]),
+            Gradient::new("grayscale", vec![
+                ColorStop::new(0.0, 0, 0, 0),
+                ColorStop::new(1.0, 255, 255, 255),
+            ]),
+        ];
+    }
+}
+
+impl Interpolation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Interpolation::Oklab => "oklab",
+            Interpolation::LinearRgb => "linear rgb",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return [Interpolation::Oklab, Interpolation::LinearRgb].iter().copied().find(|i| i.name() == name);
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Interpolation::Oklab => Interpolation::LinearRgb,
+            Interpolation::LinearRgb => Interpolation::Oklab,
+        }
+    }
+}
+
+impl RepeatMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RepeatMode::Repeat => "repeat",
+            RepeatMode::Mirror => "mirror",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        return [RepeatMode::Repeat, RepeatMode::Mirror].iter().copied().find(|r| r.name() == name);
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            RepeatMode::Repeat => RepeatMode::Mirror,
+            RepeatMode::Mirror => RepeatMode::Repeat,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::color;
+
+    fn three_stops(repeat: RepeatMode) -> Gradient {
+        let mut gradient: Gradient = Gradient::new("test", vec![ColorStop::new(0.0, 10, 20, 30), ColorStop::new(0.3, 200, 100, 0), ColorStop::new(1.0, 0, 255, 128)]);
+        gradient.repeat = repeat;
+        return gradient;
+    }
+
+    fn black_to_white(interpolation: Interpolation) -> Gradient {
+        let mut gradient: Gradient = Gradient::new("test", vec![ColorStop::new(0.0, 0, 0, 0), ColorStop::new(1.0, 255, 255, 255)]);
+        gradient.interpolation = interpolation;
+        return gradient;
+    }
+
+    #[test]
+    fn wrap_repeat_and_mirror() {
+        let repeat: Gradient = three_stops(RepeatMode::Repeat);
+        for (t, wrapped) in [(0.25, 0.25), (1.25, 0.25), (-0.25, 0.75), (1.0, 0.0), (3.0, 0.0)].iter() {
+            assert_eq!(repeat.wrap(*t), *wrapped, "repeat {}", t);
+        }
+        let mirror: Gradient = three_stops(RepeatMode::Mirror);
+        for (t, wrapped) in [(0.25, 0.25), (1.25, 0.75), (-0.25, 0.25), (1.0, 1.0), (2.0, 0.0), (2.5, 0.5)].iter() {
+            assert_eq!(mirror.wrap(*t), *wrapped, "mirror {}", t);
+        }
+    }
+
+    #[test]
+    fn stops_are_exact() {
+        for interpolation in [Interpolation::Oklab, Interpolation::LinearRgb].iter() {
+            let mut gradient: Gradient = three_stops(RepeatMode::Mirror);
+            gradient.interpolation = *interpolation;
+            assert_eq!(gradient.sample(0.0), (10, 20, 30));
+            assert_eq!(gradient.sample(0.3), (200, 100, 0));
+            assert_eq!(gradient.sample(1.0), (0, 255, 128));
+        }
+    }
+
+    #[test]
+    fn midpoints_depend_on_the_interpolation() {
+        // linear light halfway between black and white, oklab goes halfway in lightness which is the cube root
+        let linear: Rgb = black_to_white(Interpolation::LinearRgb).sample_rgb(0.5);
+        let oklab: Rgb = black_to_white(Interpolation::Oklab).sample_rgb(0.5);
+        for (value, expected) in [(linear, color::linear_to_srgb(0.5)), (oklab, color::linear_to_srgb(0.125))].iter() {
+            assert!((value.r - expected).abs() < 1e-3, "{} instead of {}", value.r, expected);
+            assert!((value.r - value.g).abs() < 1e-6 && (value.g - value.b).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn stops_are_sorted() {
+        let sorted: Gradient = three_stops(RepeatMode::Repeat);
+        let unsorted: Gradient = Gradient::new("test", vec![ColorStop::new(1.0, 0, 255, 128), ColorStop::new(0.0, 10, 20, 30), ColorStop::new(0.3, 200, 100, 0)]);
+        let positions: Vec<f64> = unsorted.stops().iter().map(|stop| stop.position).collect();
+        assert_eq!(positions, vec![0.0, 0.3, 1.0]);
+        for i in 0..=20 {
+            assert_eq!(unsorted.sample(i as f64 / 20.0), sorted.sample(i as f64 / 20.0));
+        }
+    }
+
+    #[test]
+    fn lookup_table_ends() {
+        let table: Vec<(u8, u8, u8)> = three_stops(RepeatMode::Mirror).lookup_table(256);
+        assert_eq!((table.len(), table[0], table[255]), (256, (10, 20, 30), (0, 255, 128)));
+        // position 1 is the start again when the palette repeats
+        assert_eq!(three_stops(RepeatMode::Repeat).lookup_table(256)[255], (10, 20, 30));
+        assert_eq!(three_stops(RepeatMode::Mirror).lookup_table(1), vec![(10, 20, 30)]);
+        assert!(three_stops(RepeatMode::Mirror).lookup_table(0).is_empty());
+    }
+}
@@ -1,99 +1,631 @@
-use std::rc::Rc;
+use std::{any::{Any, type_name}, cell::{Cell, RefCell}, rc::Rc};
 
-use sfml::{graphics::{RectangleShape, Transformable, Shape, Drawable, Text, Font, Color}, system::Vector2f, SfBox, window::mouse};
+use sfml::{graphics::{RectangleShape, CircleShape, Transformable, Shape, Drawable, Text, Font, Color}, system::Vector2f, SfBox, window::{Event, Key, mouse, clipboard}};
 
 use crate::{gui_traits::GuiComponent, colorscheme::{Colorscheme, ColorSchemeNames}};
 
 
+// a widget and the widgets nested in it, children are drawn after their parent
+struct Node {
+    id: String,
+    widget: Box<dyn GuiComponent>,
+    children: Vec<Node>,
+    // where the layout put it, for the focus ring
+    position: (f32, f32),
+}
+
+// turns a widget's new value into a message of the app
+type Handler<T> = Box<dyn Fn(T) -> Box<dyn Any>>;
+
+// how a container places its children, sizes come from the children so nothing is positioned by hand
+#[derive(Clone, Copy, PartialEq)]
+pub enum Layout {
+    // top to bottom, left aligned
+    Vertical { spacing: f32 },
+    // left to right, centered on the tallest child
+    Horizontal { spacing: f32 },
+    // row by row, every column as wide as its widest cell
+    Grid { columns: usize, spacing: f32 },
+    // empty space around the children
+    Padding { left: f32, top: f32, right: f32, bottom: f32 },
+}
+
+impl Layout {
+    // offset of every child from the container and the size of the container
+    fn place(&self, sizes: &[(f32, f32)]) -> (Vec<(f32, f32)>, (f32, f32)) {
+        let mut offsets: Vec<(f32, f32)> = Vec::with_capacity(sizes.len());
+        let widest: f32 = sizes.iter().fold(0.0, |m, s| m.max(s.0));
+        let tallest: f32 = sizes.iter().fold(0.0, |m, s| m.max(s.1));
+        match *self {
+            Layout::Vertical { spacing } => {
+                let mut y: f32 = 0.0;
+                for (_, height) in sizes {
+                    offsets.push((0.0, y));
+                    y += height + spacing;
+                }
+                return (offsets, (widest, (y - spacing).max(0.0)));
+            }
+            Layout::Horizontal { spacing } => {
+                let mut x: f32 = 0.0;
+                for (width, height) in sizes {
+                    offsets.push((x, (tallest - height) / 2.0));
+                    x += width + spacing;
+                }
+                return (offsets, ((x - spacing).max(0.0), tallest));
+            }
+            Layout::Grid { columns, spacing } => {
+                let columns: usize = columns.clamp(1, sizes.len().max(1));
+                let mut widths: Vec<f32> = vec![0.0; columns];
+                let mut heights: Vec<f32> = vec![0.0; sizes.len().div_ceil(columns)];
+                for (i, (width, height)) in sizes.iter().enumerate() {
+                    widths[i % columns] = widths[i % columns].max(*width);
+                    heights[i / columns] = heights[i / columns].max(*height);
+                }
+                // where every column and row starts
+                let starts = |lengths: &[f32]| -> Vec<f32> {
+                    lengths.iter().scan(0.0, |start, length| {
+                        let current: f32 = *start;
+                        *start += length + spacing;
+                        Some(current)
+                    }).collect()
+                };
+                let (xs, ys) = (starts(&widths), starts(&heights));
+                for (i, (_, height)) in sizes.iter().enumerate() {
+                    let row: usize = i / columns;
+                    offsets.push((xs[i % columns], ys[row] + (heights[row] - height) / 2.0));
+                }
+                let total = |lengths: &[f32]| -> f32 { (lengths.iter().sum::<f32>() + spacing * (lengths.len() as f32 - 1.0)).max(0.0) };
+                return (offsets, (total(&widths), total(&heights)));
+            }
+            Layout::Padding { left, top, right, bottom } => {
+                offsets.resize(sizes.len(), (left, top));
+                return (offsets, (left + widest + right, top + tallest + bottom));
+            }
+        }
+    }
+}
+
 pub struct Gui {
-    components: Vec<Box<Button>>,
-    label_components: Vec<Box<Label>>,
-    pub slider_components: Vec<Box<Slider>>,
-    pub checkbox_components: Vec<Box<Checkbox>>,
+    nodes: Vec<Node>,
+    // the containers new widgets are added to, innermost last
+    parents: Vec<String>,
+    // sent by the widgets, in the order they happened, until the app takes them
+    messages: Vec<Box<dyn Any>>,
     colorscheme: Colorscheme,
     font: Rc<SfBox<Font>>,
     font_size: u32,
-    last_mouse_state: (bool, bool, bool),
+    // the widget that took the left press, it gets the mouse until the release
+    captured: Option<String>,
+    // the widget that gets the keys, tab moves it
+    focused: Option<String>,
+    // keys whose press the gui used and the widget that took it, their release is swallowed too
+    held_keys: Vec<(Key, Option<String>)>,
+    // the tree, a text or the font size changed since the last layout
+    layout_dirty: bool,
 }
 
 impl Gui{
     pub fn new(font: &str, font_size: u32) -> Self {
         let font = Rc::new(sfml::graphics::Font::from_file(font).unwrap());
-        return Gui { components: Vec::with_capacity(10), colorscheme: Colorscheme::new("default", 200), font, font_size, last_mouse_state: (false, false, false), slider_components: Vec::with_capacity(10), label_components: Vec::with_capacity(10), checkbox_components: Vec::with_capacity(10) };
+        return Gui { nodes: Vec::with_capacity(10), parents: Vec::new(), messages: Vec::new(), colorscheme: Colorscheme::new("default", 200), font, font_size, captured: None, focused: None, held_keys: Vec::new(), layout_dirty: true };
     }
 
-    pub fn update(&mut self, mouse_x: i32, mouse_y: i32, mouse_state: (bool, bool, bool)) {
-
-        if !mouse_state.0 && self.last_mouse_state.0 {
-            self.components.iter().for_each(|comp| {
-                if comp.coordinate_inside(mouse_x as f32,  mouse_y as f32) {
-                    (comp.callback)();
+    // routes a window event to the widgets, true if the gui used it and the app should ignore it.
+    // presses go to the topmost widget under the cursor, moves and the release to the one that captured the press,
+    // keys to the focused widget
+    pub fn handle_event(&mut self, event: &Event) -> bool {
+        // a text or the font size may have changed since the last frame
+        self.layout();
+        match *event {
+            Event::MouseMoved { x, y } => {
+                let (x, y) = (x as f32, y as f32);
+                let hovered: Option<String> = hit(&self.nodes, x, y);
+                let messages: &mut Vec<Box<dyn Any>> = &mut self.messages;
+                walk_mut(&mut self.nodes, &mut |node: &mut Node| {
+                    let is_hovered: bool = hovered.as_deref() == Some(node.id.as_str());
+                    node.widget.mouse_moved(x, y, is_hovered, messages);
+                });
+                return self.captured.is_some() || hovered.is_some();
+            }
+            Event::MouseButtonPressed { button, x, y } => {
+                let (x, y) = (x as f32, y as f32);
+                // the release of an earlier press got lost, outside of the window
+                if button == mouse::Button::Left {
+                    self.cancel_capture();
                 }
-            })
-        }
-        if mouse_state.0 && self.last_mouse_state.0 {
-            self.slider_components.iter_mut().for_each(|comp: &mut Box<Slider>| {
-                if comp.coordinate_inside(mouse_x as f32,  mouse_y as f32) {
-                    comp.update(mouse_x as f32);
-                    comp.is_dragging = true;
+                let target: String = match hit(&self.nodes, x, y) {
+                    Some(id) => id,
+                    None => {
+                        self.set_focus(None);
+                        return self.captured.is_some();
+                    }
+                };
+                // other buttons are swallowed so they don't reach the fractal under the widget
+                if button == mouse::Button::Left {
+                    if find(&self.nodes, &target).unwrap().widget.focusable() {
+                        self.set_focus(Some(target.clone()));
+                    }
+                    let node: &mut Node = find_mut(&mut self.nodes, &target).unwrap();
+                    if node.widget.mouse_pressed(x, y, &mut self.messages) {
+                        self.captured = Some(target);
+                    }
+                }
+                return true;
+            }
+            Event::MouseButtonReleased { button, x, y } => {
+                let (x, y) = (x as f32, y as f32);
+                if button != mouse::Button::Left || self.captured.is_none() {
+                    return self.captured.is_some() || hit(&self.nodes, x, y).is_some();
                 }
-            })
-        } 
-        if mouse_state.0 && !self.last_mouse_state.0 {
-            self.checkbox_components.iter_mut().for_each(|comp: &mut Box<Checkbox>| {
-                if comp.coordinate_inside(mouse_x as f32,  mouse_y as f32) {
-                    comp.flip();
+                // the widget may have been removed while it had the mouse
+                let id: String = self.captured.take().unwrap();
+                if let Some(node) = find_mut(&mut self.nodes, &id) {
+                    node.widget.mouse_released(x, y, &mut self.messages);
                 }
-            })
-        } 
-        if !mouse_state.0 {
-            self.slider_components.iter_mut().for_each(|comp: &mut Box<Slider>| {
-                comp.is_dragging = false;
-            })
+                return true;
+            }
+            Event::MouseWheelScrolled { wheel, delta, x, y } => {
+                let (x, y) = (x as f32, y as f32);
+                let target: String = match hit(&self.nodes, x, y) {
+                    Some(id) => id,
+                    None => return false,
+                };
+                if wheel == mouse::Wheel::VerticalWheel {
+                    let node: &mut Node = find_mut(&mut self.nodes, &target).unwrap();
+                    node.widget.mouse_scrolled(x, y, delta, &mut self.messages);
+                }
+                return true;
+            }
+            Event::KeyPressed { code, shift, ctrl, .. } => {
+                // tab and escape move the focus themselves, other keys go to the focused widget
+                let owner: Option<String> = match code {
+                    Key::Tab | Key::Escape => None,
+                    _ => self.focused.clone(),
+                };
+                let used: bool = self.key_pressed(code, shift, ctrl);
+                if used && !self.held_keys.iter().any(|(held, _)| *held == code) {
+                    self.held_keys.push((code, owner));
+                }
+                return used;
+            }
+            // to the widget that took the press, even if the focus moved on since
+            Event::KeyReleased { code, .. } => {
+                let (_, owner) = match self.held_keys.iter().position(|(held, _)| *held == code) {
+                    Some(i) => self.held_keys.remove(i),
+                    None => return false,
+                };
+                if let Some(id) = owner {
+                    if let Some(node) = find_mut(&mut self.nodes, &id) {
+                        node.widget.key_released(code, &mut self.messages);
+                    }
+                }
+                return true;
+            }
+            // the releases happen in another window and never arrive
+            Event::LostFocus => {
+                self.cancel_input();
+                return false;
+            }
+            // typed characters, after the key press that made them
+            Event::TextEntered { unicode } => {
+                if let Some(id) = self.focused.clone() {
+                    if let Some(node) = find_mut(&mut self.nodes, &id) {
+                        return node.widget.text_entered(unicode, &mut self.messages);
+                    }
+                }
+                return false;
+            }
+            _ => return false,
+        }
+    }
+
+    // ends the presses of the mouse and the keys without their releases, for when the window loses the focus or the app hides the gui
+    pub fn cancel_input(&mut self) {
+        self.cancel_capture();
+        self.release_keys(None);
+        self.held_keys.clear();
+    }
+
+    // the release happens nowhere, so a button doesn't click
+    fn cancel_capture(&mut self) {
+        if let Some(id) = self.captured.take() {
+            if let Some(node) = find_mut(&mut self.nodes, &id) {
+                node.widget.mouse_released(f32::NAN, f32::NAN, &mut self.messages);
+            }
+        }
+    }
+
+    // tells the widgets that took held keys that they are up, only the widget with the id if there is one.
+    // the keys stay held without a widget, so their release is still swallowed
+    fn release_keys(&mut self, id: Option<&str>) {
+        for (code, owner) in self.held_keys.iter_mut() {
+            if owner.is_none() || (id.is_some() && owner.as_deref() != id) {
+                continue;
+            }
+            if let Some(node) = find_mut(&mut self.nodes, &owner.take().unwrap()) {
+                node.widget.key_released(*code, &mut self.messages);
+            }
+        }
+    }
+
+    // tells the old and the new widget, a widget that was removed is skipped.
+    // the old widget gets the releases of its keys first, so a slider sends its change
+    fn set_focus(&mut self, id: Option<String>) {
+        if id == self.focused {
+            return;
+        }
+        if let Some(old) = self.focused.take() {
+            self.release_keys(Some(&old));
+            if let Some(node) = find_mut(&mut self.nodes, &old) {
+                node.widget.set_focused(false, &mut self.messages);
+            }
+        }
+        if let Some(new) = &id {
+            if let Some(node) = find_mut(&mut self.nodes, new) {
+                node.widget.set_focused(true, &mut self.messages);
+            }
+        }
+        self.focused = id;
+    }
+
+    // tab and shift tab move the focus, escape drops it, other keys go to the focused widget
+    fn key_pressed(&mut self, code: Key, shift: bool, ctrl: bool) -> bool {
+        if code == Key::Tab {
+            self.move_focus(shift);
+            return true;
+        }
+        let id: String = match &self.focused {
+            Some(id) => id.clone(),
+            None => return false,
+        };
+        if code == Key::Escape {
+            self.set_focus(None);
+            return true;
+        }
+        match find_mut(&mut self.nodes, &id) {
+            Some(node) => return node.widget.key_pressed(code, shift, ctrl, &mut self.messages),
+            // the widget was removed
+            None => {
+                self.set_focus(None);
+                return false;
+            }
+        }
+    }
+
+    // to the next focusable widget in drawing order, or the previous one, wrapping around
+    fn move_focus(&mut self, backwards: bool) {
+        let mut focusable: Vec<String> = Vec::new();
+        walk(&self.nodes, &mut |node: &Node| {
+            if node.widget.focusable() {
+                focusable.push(node.id.clone());
+            }
+        });
+        if focusable.is_empty() {
+            self.set_focus(None);
+            return;
+        }
+        let count: usize = focusable.len();
+        let current: Option<usize> = self.focused.as_ref().and_then(|id| focusable.iter().position(|f| f == id));
+        let next: usize = match (current, backwards) {
+            (Some(i), false) => (i + 1) % count,
+            (Some(i), true) => (i + count - 1) % count,
+            (None, false) => 0,
+            (None, true) => count - 1,
+        };
+        self.set_focus(Some(focusable.swap_remove(next)));
+    }
+
+    // queues a message as if a widget had sent it, so keys and widgets can share the handling
+    pub fn send<M: Any>(&mut self, message: M) {
+        self.messages.push(Box::new(message));
+    }
+
+    // the messages of type M, others stay queued
+    pub fn take_messages<M: Any>(&mut self) -> Vec<M> {
+        let mut taken: Vec<M> = Vec::new();
+        let mut kept: Vec<Box<dyn Any>> = Vec::new();
+        for message in self.messages.drain(..) {
+            match message.downcast::<M>() {
+                Ok(m) => taken.push(*m),
+                Err(other) => kept.push(other),
+            }
+        }
+        self.messages = kept;
+        return taken;
+    }
+
+    // positions every widget from the sizes of the widgets in its containers, widgets outside of a container are at the origin.
+    // does nothing until something that can move a widget changed
+    pub fn layout(&mut self) {
+        if !self.layout_dirty {
+            return;
+        }
+        self.layout_dirty = false;
+        let mut nodes: Vec<Node> = std::mem::take(&mut self.nodes);
+        for node in nodes.iter_mut() {
+            arrange(node, 0.0, 0.0, self);
+        }
+        self.nodes = nodes;
+    }
+
+    // ids are unique in the whole tree, adding one twice is a bug.
+    // goes into the innermost open container, if there is one
+    pub fn add(&mut self, id: &str, widget: Box<dyn GuiComponent>) {
+        if let Some(parent) = self.parents.last().cloned() {
+            self.add_child(&parent, id, widget);
+            return;
+        }
+        assert!(!self.contains(id), "gui already has a widget \"{}\"", id);
+        self.layout_dirty = true;
+        self.nodes.push(Node { id: id.to_string(), widget, children: Vec::new(), position: (0.0, 0.0) });
+    }
+
+    pub fn add_child(&mut self, parent: &str, id: &str, widget: Box<dyn GuiComponent>) {
+        assert!(!self.contains(id), "gui already has a widget \"{}\"", id);
+        self.layout_dirty = true;
+        match find_mut(&mut self.nodes, parent) {
+            Some(node) => node.children.push(Node { id: id.to_string(), widget, children: Vec::new(), position: (0.0, 0.0) }),
+            None => panic!("gui has no widget \"{}\" to add \"{}\" to", parent, id),
         }
-        self.last_mouse_state = mouse_state;
     }
 
+    // removes the widget together with its children, false if there was none
     #[allow(dead_code)]
-    pub fn add_button(&mut self, x: f32, y: f32, width: f32, height: f32, text: String, callback: impl Fn() + 'static) {
-        Button::create(self, x, y, width, height, text, callback);
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.layout_dirty = true;
+        return remove(&mut self.nodes, id);
+    }
+
+    // moves the widget to position index among its siblings, later ones are drawn on top
+    #[allow(dead_code)]
+    pub fn reorder(&mut self, id: &str, index: usize) {
+        self.layout_dirty = true;
+        reorder(&mut self.nodes, id, index);
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        return find(&self.nodes, id).is_some();
+    }
+
+    // the widget with this id, a missing id or another type of widget is a bug
+    pub fn get<T: GuiComponent>(&self, id: &str) -> &T {
+        return get(&self.nodes, id);
+    }
+
+    // the caller may change what the widget shows, like the text of a label, and with it its size
+    pub fn get_mut<T: GuiComponent>(&mut self, id: &str) -> &mut T {
+        self.layout_dirty = true;
+        let node: &mut Node = find_mut(&mut self.nodes, id).unwrap_or_else(|| panic!("gui has no widget \"{}\"", id));
+        return node.widget.as_any_mut().downcast_mut::<T>().unwrap_or_else(|| panic!("widget \"{}\" is not a {}", id, type_name::<T>()));
+    }
+
+    // the widgets added until the matching end_container are its children
+    pub fn begin_container(&mut self, id: &str, layout: Layout) {
+        Container::create(self, id, layout);
+        self.parents.push(id.to_string());
+    }
+
+    pub fn end_container(&mut self) {
+        self.parents.pop().expect("end_container without begin_container");
+    }
+
+    pub fn add_button(&mut self, id: &str, width: f32, height: f32, text: String) {
+        Button::create(self, id, width, height, text);
+    }
+
+    pub fn add_slider(&mut self, id: &str, length: f32, min_value: f32, max_value: f32) {
+        Slider::create(self, id, length, min_value, max_value);
+    }
+
+    pub fn add_label(&mut self, id: &str, text: String) {
+        Label::create(self, id, text);
+    }
+
+    pub fn add_checkbox(&mut self, id: &str) {
+        Checkbox::create(self, id);
+    }
+
+    pub fn add_text_input(&mut self, id: &str, width: f32, mode: InputMode) {
+        TextInput::create(self, id, width, mode);
+    }
+
+    pub fn add_dropdown(&mut self, id: &str, width: f32, options: Vec<String>) {
+        Dropdown::create(self, id, width, options);
+    }
+
+    pub fn add_radio_group(&mut self, id: &str, options: Vec<String>) {
+        RadioGroup::create(self, id, options);
+    }
+
+    pub fn set_font_size(&mut self, font_size: u32) {
+        self.font_size = font_size;
+        self.layout_dirty = true;
+    }
+
+    pub fn font_size(&self) -> u32 {
+        return self.font_size;
     }
 
-    pub fn add_slider(&mut self, x: f32, y: f32, length: f32, min_value: f32, max_value: f32) {
-        Slider::create(self, x, y, length, min_value, max_value);
+    pub fn set_colorscheme(&mut self, colorscheme: Colorscheme) {
+        self.colorscheme = colorscheme;
     }
 
-    pub fn add_label(&mut self, x: f32, y: f32, text: String) {
-        Label::create(self, x, y, text);
+    // popups cover other widgets, so they don't let them show through
+    fn opaque(&self, color: ColorSchemeNames) -> Color {
+        let mut c: Color = self.colorscheme.sfml_color(color);
+        c.a = 255;
+        return c;
     }
 
-    pub fn add_checkbox(&mut self, x: f32, y: f32) {
-        Checkbox::create(self, x, y);
+    // fill of a widget in its current state
+    fn state_color(&self, hovered: bool, pressed: bool) -> Color {
+        if pressed {
+            return self.colorscheme.sfml_color(ColorSchemeNames::Selected);
+        }
+        if hovered {
+            return self.colorscheme.sfml_color(ColorSchemeNames::Hover);
+        }
+        return self.colorscheme.sfml_color(ColorSchemeNames::Fill);
     }
 }
 
 impl Drawable for Gui {
     fn draw<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, target: &mut dyn sfml::graphics::RenderTarget, states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>) {
-        self.components.iter().for_each(|comp| comp.render(target, states, self));
-        self.slider_components.iter().for_each(|comp| comp.render(target, states, self));
-        self.label_components.iter().for_each(|comp| comp.render(target, states, self));
-        self.checkbox_components.iter().for_each(|comp| comp.render(target, states, self));
+        walk(&self.nodes, &mut |node: &Node| node.widget.render(target, states, self));
+        walk(&self.nodes, &mut |node: &Node| node.widget.render_overlay(target, states, self));
+        // the ring goes around the widget with a gap, on top of everything
+        if let Some(node) = self.focused.as_ref().and_then(|id| find(&self.nodes, id)) {
+            let (width, height) = measure(node, self);
+            let mut ring: RectangleShape = RectangleShape::new();
+            ring.set_position(Vector2f::new(node.position.0 - 3.0, node.position.1 - 3.0));
+            ring.set_size(Vector2f::new(width + 6.0, height + 6.0));
+            ring.set_fill_color(Color::TRANSPARENT);
+            ring.set_outline_color(self.colorscheme.sfml_color(ColorSchemeNames::Selected));
+            ring.set_outline_thickness(2.0);
+            target.draw(&ring);
+        }
+    }
+}
+
+// containers are as big as the layout of their children
+fn measure(node: &Node, gui: &Gui) -> (f32, f32) {
+    match node.widget.as_any().downcast_ref::<Container>() {
+        Some(container) => {
+            let sizes: Vec<(f32, f32)> = node.children.iter().map(|child| measure(child, gui)).collect();
+            return container.layout.place(&sizes).1;
+        }
+        None => return node.widget.size(gui),
+    }
+}
+
+fn arrange(node: &mut Node, x: f32, y: f32, gui: &Gui) {
+    node.widget.set_position(x, y);
+    node.position = (x, y);
+    let layout: Layout = match node.widget.as_any().downcast_ref::<Container>() {
+        Some(container) => container.layout,
+        None => return,
+    };
+    let sizes: Vec<(f32, f32)> = node.children.iter().map(|child| measure(child, gui)).collect();
+    let (offsets, _) = layout.place(&sizes);
+    for (child, (dx, dy)) in node.children.iter_mut().zip(offsets) {
+        arrange(child, x + dx, y + dy, gui);
+    }
+}
+
+// the id of the widget drawn on top at x, y
+fn hit(nodes: &[Node], x: f32, y: f32) -> Option<String> {
+    let mut top: Option<String> = None;
+    let mut overlay: Option<String> = None;
+    walk(nodes, &mut |node: &Node| {
+        if node.widget.coordinate_inside(x, y) {
+            top = Some(node.id.clone());
+        }
+        if node.widget.overlay_inside(x, y) {
+            overlay = Some(node.id.clone());
+        }
+    });
+    return overlay.or(top);
+}
+
+// depth first, parents before their children
+fn walk(nodes: &[Node], f: &mut dyn FnMut(&Node)) {
+    for node in nodes {
+        f(node);
+        walk(&node.children, f);
+    }
+}
+
+fn walk_mut(nodes: &mut [Node], f: &mut dyn FnMut(&mut Node)) {
+    for node in nodes.iter_mut() {
+        f(node);
+        walk_mut(&mut node.children, f);
+    }
+}
+
+fn find<'a>(nodes: &'a [Node], id: &str) -> Option<&'a Node> {
+    for node in nodes {
+        if node.id == id {
+            return Some(node);
+        }
+        if let Some(found) = find(&node.children, id) {
+            return Some(found);
+        }
+    }
+    return None;
+}
+
+fn find_mut<'a>(nodes: &'a mut [Node], id: &str) -> Option<&'a mut Node> {
+    for node in nodes.iter_mut() {
+        if node.id == id {
+            return Some(node);
+        }
+        if let Some(found) = find_mut(&mut node.children, id) {
+            return Some(found);
+        }
+    }
+    return None;
+}
+
+fn get<'a, T: GuiComponent>(nodes: &'a [Node], id: &str) -> &'a T {
+    let node: &Node = find(nodes, id).unwrap_or_else(|| panic!("gui has no widget \"{}\"", id));
+    return node.widget.as_any().downcast_ref::<T>().unwrap_or_else(|| panic!("widget \"{}\" is not a {}", id, type_name::<T>()));
+}
+
+#[allow(dead_code)]
+fn remove(nodes: &mut Vec<Node>, id: &str) -> bool {
+    match siblings_mut(nodes, id) {
+        Some(siblings) => {
+            siblings.retain(|node| node.id != id);
+            true
+        }
+        None => false,
+    }
+}
+
+// an index past the end moves it to the end
+#[allow(dead_code)]
+fn reorder(nodes: &mut Vec<Node>, id: &str, index: usize) {
+    if let Some(siblings) = siblings_mut(nodes, id) {
+        let from: usize = siblings.iter().position(|node| node.id == id).unwrap();
+        let node: Node = siblings.remove(from);
+        let index: usize = index.min(siblings.len());
+        siblings.insert(index, node);
+    }
+}
+
+// the list the widget is in, for removing and reordering
+#[allow(dead_code)]
+fn siblings_mut<'a>(nodes: &'a mut Vec<Node>, id: &str) -> Option<&'a mut Vec<Node>> {
+    if nodes.iter().any(|node| node.id == id) {
+        return Some(nodes);
+    }
+    for node in nodes.iter_mut() {
+        if let Some(found) = siblings_mut(&mut node.children, id) {
+            return Some(found);
+        }
     }
+    return None;
 }
 
-struct Button {
+pub struct Button {
     pos_x: f32,
     pos_y: f32,
     dim_width: f32,
     dim_height: f32,
     text: String,
-    callback: Box<dyn Fn()>,
+    on_click: Option<Handler<()>>,
+    hovered: bool,
+    pressed: bool,
 }
 
 impl Button {
-    pub fn create(gui: &mut Gui, x: f32, y: f32, width: f32, height: f32, text: String, callback: impl Fn()  + 'static) {
-        let btn = Box::new(Button{pos_x: x, pos_y: y, dim_height: height, dim_width: width, text, callback: Box::new(callback)});
-        gui.components.push(btn);
+    pub fn create(gui: &mut Gui, id: &str, width: f32, height: f32, text: String) {
+        let btn = Box::new(Button{pos_x: 0.0, pos_y: 0.0, dim_height: height, dim_width: width, text, on_click: None, hovered: false, pressed: false});
+        gui.add(id, btn);
+    }
+
+    pub fn on_click<M: Any>(&mut self, handler: impl Fn() -> M + 'static) {
+        self.on_click = Some(Box::new(move |_| Box::new(handler())));
     }
 }
 
@@ -103,7 +635,8 @@ impl GuiComponent for Button {
         let mut b: RectangleShape = RectangleShape::new();
         b.set_position(Vector2f::new(self.pos_x, self.pos_y));
         b.set_size(Vector2f::new(self.dim_width, self.dim_height));
-        b.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Fill));
+        // shown pressed only while the cursor is still on it, releasing elsewhere doesn't click
+        b.set_fill_color(gui.state_color(self.hovered, self.pressed && self.hovered));
         b.set_outline_color(gui.colorscheme.sfml_color(ColorSchemeNames::Outline));
         b.set_outline_thickness(1.0);
         target.draw(&b);
@@ -120,6 +653,55 @@ impl GuiComponent for Button {
         }
         false
     }
+
+    fn mouse_moved(&mut self, _x: f32, _y: f32, hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        self.hovered = hovered;
+    }
+
+    // a widget stays pressed while the button is held after a press that started on it
+    fn mouse_pressed(&mut self, _x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        self.pressed = true;
+        return true;
+    }
+
+    // releasing elsewhere doesn't click
+    fn mouse_released(&mut self, x: f32, y: f32, messages: &mut Vec<Box<dyn Any>>) {
+        self.pressed = false;
+        if let (true, Some(handler)) = (self.coordinate_inside(x, y), &self.on_click) {
+            messages.push(handler(()));
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn key_pressed(&mut self, code: Key, _shift: bool, _ctrl: bool, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        if code != Key::Enter {
+            return false;
+        }
+        if let Some(handler) = &self.on_click {
+            messages.push(handler(()));
+        }
+        return true;
+    }
+
+    fn size(&self, _gui: &Gui) -> (f32, f32) {
+        return (self.dim_width, self.dim_height);
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
 }
 
 pub struct Slider {
@@ -129,35 +711,40 @@ pub struct Slider {
     pub value: f32,
     max_value: f32,
     min_value: f32,
-    last_value: f32,
+    // the value when the drag started, a drag that ends elsewhere is a change
+    drag_start: f32,
     is_dragging: bool,
-    last_is_dragging: bool,
+    // an arrow key is held, it counts as a drag
+    key_held: bool,
+    on_change: Option<Handler<f32>>,
+    hovered: bool,
 }
 
 impl Slider {
-    pub fn create(gui: &mut Gui, x: f32, y: f32, length: f32, min_value: f32, max_value: f32) {
-        let slider = Box::new(Slider{pos_x: x, pos_y: y, length, value: (max_value + min_value) / 2.0, max_value, min_value, last_value: (max_value + min_value) / 2.0, is_dragging: false, last_is_dragging: false});
-        gui.slider_components.push(slider);
+    pub fn create(gui: &mut Gui, id: &str, length: f32, min_value: f32, max_value: f32) {
+        let slider = Box::new(Slider{pos_x: 0.0, pos_y: 0.0, length, value: (max_value + min_value) / 2.0, max_value, min_value, drag_start: 0.0, is_dragging: false, key_held: false, on_change: None, hovered: false});
+        gui.add(id, slider);
     }
 
-    pub fn update(&mut self, mouse_x: f32) {
-        self.last_value = self.value;
-        self.value = (mouse_x - self.pos_x) / self.length * (self.max_value - self.min_value) + self.min_value;
+    pub fn drag_to(&mut self, mouse_x: f32) {
+        self.value = ((mouse_x - self.pos_x) / self.length * (self.max_value - self.min_value) + self.min_value).clamp(self.min_value, self.max_value);
     }
 
-    pub fn get_value_changed(&mut self) -> bool {
-        if mouse::Button::Left.is_pressed() {
-            return false;
-        }
-        if self.value != self.last_value {
-            self.value = self.last_value;
-            return true;
-        }
-        if self.last_is_dragging != self.is_dragging {
-            self.last_is_dragging = self.is_dragging;
-            return true;
+    // for loading values, doesn't send a message
+    pub fn set_value(&mut self, value: f32) {
+        self.value = value.clamp(self.min_value, self.max_value);
+    }
+
+    // sent once the drag ends, so the app doesn't redo its work every frame of the drag
+    pub fn on_change<M: Any>(&mut self, handler: impl Fn(f32) -> M + 'static) {
+        self.on_change = Some(Box::new(move |value| Box::new(handler(value))));
+    }
+
+    fn finish_change(&mut self, messages: &mut Vec<Box<dyn Any>>) {
+        if let (true, Some(handler)) = (self.value != self.drag_start, &self.on_change) {
+            messages.push(handler(self.value));
         }
-        return false;
+        self.drag_start = self.value;
     }
 }
 
@@ -167,14 +754,14 @@ impl GuiComponent for Slider {
         let mut b: RectangleShape = RectangleShape::new();
         b.set_position(Vector2f::new(self.pos_x, self.pos_y));
         b.set_size(Vector2f::new(self.length, 10.0));
-        b.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Fill));
+        b.set_fill_color(gui.state_color(self.hovered || self.is_dragging, false));
         b.set_outline_color(gui.colorscheme.sfml_color(ColorSchemeNames::Outline));
         b.set_outline_thickness(1.0);
         target.draw(&b);
         let mut b: RectangleShape = RectangleShape::new();
         b.set_position(Vector2f::new(self.pos_x + self.length * (self.value - self.min_value) / (self.max_value - self.min_value), self.pos_y));
         b.set_size(Vector2f::new(10.0, 10.0));
-        b.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Fill));
+        b.set_fill_color(gui.state_color(self.hovered, self.is_dragging));
         b.set_outline_color(gui.colorscheme.sfml_color(ColorSchemeNames::Outline));
         b.set_outline_thickness(1.0);
         target.draw(&b);
@@ -186,22 +773,101 @@ impl GuiComponent for Slider {
         }
         false
     }
+
+    // the thumb follows the cursor even when it leaves the slider, until the release
+    fn mouse_moved(&mut self, x: f32, _y: f32, hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        self.hovered = hovered;
+        if self.is_dragging {
+            self.drag_to(x);
+        }
+    }
+
+    fn mouse_pressed(&mut self, x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        self.drag_start = self.value;
+        self.drag_to(x);
+        self.is_dragging = true;
+        return true;
+    }
+
+    fn mouse_released(&mut self, _x: f32, _y: f32, messages: &mut Vec<Box<dyn Any>>) {
+        self.is_dragging = false;
+        self.finish_change(messages);
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    // a hundredth of the range per step, a tenth with shift. like a drag the change is sent on the release
+    fn key_pressed(&mut self, code: Key, shift: bool, _ctrl: bool, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        let direction: f32 = match code {
+            Key::Left | Key::Down => -1.0,
+            Key::Right | Key::Up => 1.0,
+            _ => return false,
+        };
+        if !self.is_dragging && !self.key_held {
+            self.drag_start = self.value;
+        }
+        self.key_held = true;
+        let step: f32 = (self.max_value - self.min_value) / if shift { 10.0 } else { 100.0 };
+        self.set_value(self.value + direction * step);
+        return true;
+    }
+
+    fn key_released(&mut self, _code: Key, messages: &mut Vec<Box<dyn Any>>) {
+        self.key_held = false;
+        if !self.is_dragging {
+            self.finish_change(messages);
+        }
+    }
+
+    fn size(&self, _gui: &Gui) -> (f32, f32) {
+        return (self.length, 10.0);
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
 }
 
 pub struct Checkbox {
     pos_x: f32,
     pos_y: f32,
     pub state: bool,
+    on_change: Option<Handler<bool>>,
+    hovered: bool,
+    pressed: bool,
 }
 
 impl Checkbox {
-    pub fn create(gui: &mut Gui, x: f32, y: f32) {
-        let checkbox = Box::new(Checkbox{pos_x: x, pos_y: y, state: false});
-        gui.checkbox_components.push(checkbox);
+    pub fn create(gui: &mut Gui, id: &str) {
+        let checkbox = Box::new(Checkbox{pos_x: 0.0, pos_y: 0.0, state: false, on_change: None, hovered: false, pressed: false});
+        gui.add(id, checkbox);
     }
 
-    pub fn flip(&mut self) {
+    // for loading values, doesn't send a message
+    pub fn set_state(&mut self, state: bool) {
+        self.state = state;
+    }
+
+    pub fn on_change<M: Any>(&mut self, handler: impl Fn(bool) -> M + 'static) {
+        self.on_change = Some(Box::new(move |state| Box::new(handler(state))));
+    }
+
+    fn toggle(&mut self, messages: &mut Vec<Box<dyn Any>>) {
         self.state = !self.state;
+        if let Some(handler) = &self.on_change {
+            messages.push(handler(self.state));
+        }
     }
 }
 
@@ -212,10 +878,16 @@ impl GuiComponent for Checkbox {
         cb.set_size(Vector2f::new(20.0, 20.0));
         let color = match self.state {
             true => Color::GREEN,
-            false => gui.colorscheme.sfml_color(ColorSchemeNames::Fill),
+            false => gui.state_color(self.hovered, self.pressed && self.hovered),
         };
         cb.set_fill_color(color);
-        cb.set_outline_color(gui.colorscheme.sfml_color(ColorSchemeNames::Outline));
+        // a checked box keeps its green, the outline shows hover and press instead
+        let outline: ColorSchemeNames = match (self.state, self.pressed && self.hovered, self.hovered) {
+            (true, true, _) => ColorSchemeNames::Selected,
+            (true, false, true) => ColorSchemeNames::Hover,
+            _ => ColorSchemeNames::Outline,
+        };
+        cb.set_outline_color(gui.colorscheme.sfml_color(outline));
         cb.set_outline_thickness(1.0);
         target.draw(&cb);
     }
@@ -226,6 +898,50 @@ impl GuiComponent for Checkbox {
         }
         false
     }
+
+    fn mouse_moved(&mut self, _x: f32, _y: f32, hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        self.hovered = hovered;
+    }
+
+    // flips on the press, the release only ends the pressed look
+    fn mouse_pressed(&mut self, _x: f32, _y: f32, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        self.toggle(messages);
+        self.pressed = true;
+        return true;
+    }
+
+    fn mouse_released(&mut self, _x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) {
+        self.pressed = false;
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn key_pressed(&mut self, code: Key, _shift: bool, _ctrl: bool, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        if code != Key::Space {
+            return false;
+        }
+        self.toggle(messages);
+        return true;
+    }
+
+    fn size(&self, _gui: &Gui) -> (f32, f32) {
+        return (20.0, 20.0);
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
 }
 
 pub struct Label {
@@ -235,9 +951,13 @@ pub struct Label {
 }
 
 impl Label {
-    pub fn create(gui: &mut Gui, x: f32, y: f32, text: String) {
-        let label = Box::new(Label{pos_x: x, pos_y: y, text});
-        gui.label_components.push(label);
+    pub fn create(gui: &mut Gui, id: &str, text: String) {
+        let label = Box::new(Label{pos_x: 0.0, pos_y: 0.0, text});
+        gui.add(id, label);
+    }
+
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
     }
 }
 
@@ -253,4 +973,1049 @@ impl GuiComponent for Label {
     fn coordinate_inside(&self, _x: f32, _y: f32) -> bool {
         false
     }
-}
\ No newline at end of file
+
+    fn size(&self, gui: &Gui) -> (f32, f32) {
+        // the line height, so an empty label still takes its row
+        let text = Text::new(&self.text, &gui.font, gui.font_size);
+        return (text.local_bounds().left + text.local_bounds().width, gui.font.line_spacing(gui.font_size));
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+// what a text input accepts, the numeric modes refuse edits that can't become a number
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq)]
+pub enum InputMode {
+    Text,
+    Integer,
+    Float,
+    // a float with an optional exponent like 1.5e-3
+    Scientific,
+}
+
+impl InputMode {
+    // whether the text is a finished value, and whether typing more could still make it one
+    fn check(&self, text: &str) -> (bool, bool) {
+        if *self == InputMode::Text {
+            return (true, true);
+        }
+        let chars: Vec<char> = text.chars().collect();
+        let digits = |i: &mut usize| -> usize {
+            let start: usize = *i;
+            while *i < chars.len() && chars[*i].is_ascii_digit() {
+                *i += 1;
+            }
+            return *i - start;
+        };
+        let sign = |i: &mut usize| {
+            if *i < chars.len() && (chars[*i] == '+' || chars[*i] == '-') {
+                *i += 1;
+            }
+        };
+        let mut i: usize = 0;
+        sign(&mut i);
+        let mut mantissa: usize = digits(&mut i);
+        if *self != InputMode::Integer && i < chars.len() && chars[i] == '.' {
+            i += 1;
+            mantissa += digits(&mut i);
+        }
+        let mut exponent: Option<usize> = None;
+        if *self == InputMode::Scientific && i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+            i += 1;
+            sign(&mut i);
+            exponent = Some(digits(&mut i));
+        }
+        let partial: bool = i == chars.len() && (exponent.is_none() || mantissa > 0);
+        return (partial && mantissa > 0 && exponent != Some(0), partial);
+    }
+}
+
+pub struct TextInput {
+    pos_x: f32,
+    pos_y: f32,
+    width: f32,
+    text: String,
+    mode: InputMode,
+    // in characters, the selection is between the anchor and the cursor
+    cursor: usize,
+    anchor: usize,
+    // what the app last heard, an unfinished number is reverted to it when the focus leaves
+    submitted: String,
+    // x of every character boundary at the last render, for placing the cursor with the mouse
+    boundaries: RefCell<Vec<f32>>,
+    // how far a text wider than the box is moved left, so the cursor stays inside
+    scroll: Cell<f32>,
+    // depends on the font, known once the layout measured it
+    height: Cell<f32>,
+    on_change: Option<Handler<String>>,
+    hovered: bool,
+    focused: bool,
+    selecting: bool,
+}
+
+impl TextInput {
+    pub fn create(gui: &mut Gui, id: &str, width: f32, mode: InputMode) {
+        let input = Box::new(TextInput{pos_x: 0.0, pos_y: 0.0, width, text: String::new(), mode, cursor: 0, anchor: 0, submitted: String::new(), boundaries: RefCell::new(Vec::new()), scroll: Cell::new(0.0), height: Cell::new(0.0), on_change: None, hovered: false, focused: false, selecting: false});
+        gui.add(id, input);
+    }
+
+    // sent on enter and when the focus leaves, only with text that is a finished value of the mode
+    pub fn on_change<M: Any>(&mut self, handler: impl Fn(String) -> M + 'static) {
+        self.on_change = Some(Box::new(move |text| Box::new(handler(text))));
+    }
+
+    // for loading values, doesn't send a message
+    pub fn set_text(&mut self, text: String) {
+        self.cursor = text.chars().count();
+        self.anchor = self.cursor;
+        self.submitted = text.clone();
+        self.text = text;
+    }
+
+    pub fn text(&self) -> &str {
+        return &self.text;
+    }
+
+    fn length(&self) -> usize {
+        return self.text.chars().count();
+    }
+
+    fn byte_index(&self, index: usize) -> usize {
+        return self.text.char_indices().nth(index).map(|(i, _)| i).unwrap_or(self.text.len());
+    }
+
+    fn selection(&self) -> (usize, usize) {
+        return (self.cursor.min(self.anchor), self.cursor.max(self.anchor));
+    }
+
+    fn selected_text(&self) -> String {
+        let (start, end) = self.selection();
+        return self.text[self.byte_index(start)..self.byte_index(end)].to_string();
+    }
+
+    // replaces the selection, refused if the mode could never accept the result
+    fn insert(&mut self, inserted: &str) {
+        let (start, end) = self.selection();
+        let mut text: String = self.text.clone();
+        text.replace_range(self.byte_index(start)..self.byte_index(end), inserted);
+        if !self.mode.check(&text).1 {
+            return;
+        }
+        self.text = text;
+        self.cursor = start + inserted.chars().count();
+        self.anchor = self.cursor;
+    }
+
+    // the selection, or the character before or after the cursor
+    fn delete(&mut self, forward: bool) {
+        if self.cursor == self.anchor {
+            match forward {
+                true if self.cursor < self.length() => self.anchor = self.cursor + 1,
+                false if self.cursor > 0 => self.anchor = self.cursor - 1,
+                _ => return,
+            }
+        }
+        self.insert("");
+    }
+
+    fn submit(&mut self, messages: &mut Vec<Box<dyn Any>>) {
+        if !self.mode.check(&self.text).0 || self.text == self.submitted {
+            return;
+        }
+        self.submitted = self.text.clone();
+        if let Some(handler) = &self.on_change {
+            messages.push(handler(self.text.clone()));
+        }
+    }
+
+    // line breaks are dropped, numbers are trimmed
+    fn paste(&mut self, pasted: &str) {
+        let pasted: String = pasted.chars().filter(|c| !c.is_control()).collect();
+        match self.mode {
+            InputMode::Text => self.insert(&pasted),
+            _ => self.insert(pasted.trim()),
+        }
+    }
+
+    // offsets are the x of every character boundary from the start of the text, width is what the box shows of it.
+    // moves the text only as far as the cursor needs and never past its end
+    fn scroll_to_cursor(&self, offsets: &[f32], width: f32) -> f32 {
+        let cursor: f32 = offsets[self.cursor];
+        let end: f32 = offsets[offsets.len() - 1];
+        let scroll: f32 = self.scroll.get().min(cursor).max(cursor - width).min(end - width).max(0.0);
+        self.scroll.set(scroll);
+        return scroll;
+    }
+
+    // the character boundary closest to x
+    fn index_at(&self, x: f32) -> usize {
+        let boundaries = self.boundaries.borrow();
+        let closest = boundaries.iter().enumerate().min_by(|a, b| (a.1 - x).abs().total_cmp(&(b.1 - x).abs()));
+        return closest.map(|(i, _)| i).unwrap_or(0).min(self.length());
+    }
+}
+
+impl GuiComponent for TextInput {
+    fn render<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, target: &mut dyn sfml::graphics::RenderTarget, _states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, gui: &Gui) {
+        let line: f32 = gui.font.line_spacing(gui.font_size);
+        let mut b: RectangleShape = RectangleShape::new();
+        b.set_position(Vector2f::new(self.pos_x, self.pos_y));
+        b.set_size(Vector2f::new(self.width, line + 6.0));
+        b.set_fill_color(gui.state_color(self.hovered, false));
+        // an unfinished number has a red outline until it is fixed or reverted
+        let finished: bool = self.text.is_empty() || self.mode.check(&self.text).0;
+        b.set_outline_color(if finished { gui.colorscheme.sfml_color(ColorSchemeNames::Outline) } else { Color::RED });
+        b.set_outline_thickness(1.0);
+        target.draw(&b);
+        // a text wider than the box scrolls, only the characters that fit in it are drawn
+        let inner: f32 = self.width - 8.0;
+        let full = Text::new(&self.text, &gui.font, gui.font_size);
+        let offsets: Vec<f32> = (0..=self.length()).map(|i| full.find_character_pos(i).x).collect();
+        let scroll: f32 = self.scroll_to_cursor(&offsets, inner);
+        let boundaries: Vec<f32> = offsets.iter().map(|x| self.pos_x + 4.0 + x - scroll).collect();
+        let first: usize = offsets.iter().position(|x| *x >= scroll).unwrap_or(0);
+        let last: usize = offsets.iter().rposition(|x| *x <= scroll + inner).unwrap_or(0).max(first);
+        let visible: String = self.text.chars().skip(first).take(last - first).collect();
+        let mut text = Text::new(&visible, &gui.font, gui.font_size);
+        text.set_position(Vector2f::new(boundaries[first], self.pos_y + 3.0));
+        text.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Text));
+        if self.focused {
+            let (start, end) = self.selection();
+            let (left, right) = (boundaries[start].max(self.pos_x + 4.0), boundaries[end].min(self.pos_x + 4.0 + inner));
+            let mut selection: RectangleShape = RectangleShape::new();
+            selection.set_position(Vector2f::new(left, self.pos_y + 3.0));
+            selection.set_size(Vector2f::new((right - left).max(0.0), line));
+            selection.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Selected));
+            target.draw(&selection);
+            let mut cursor: RectangleShape = RectangleShape::new();
+            cursor.set_position(Vector2f::new(boundaries[self.cursor], self.pos_y + 3.0));
+            cursor.set_size(Vector2f::new(1.0, line));
+            cursor.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Text));
+            target.draw(&cursor);
+        }
+        target.draw(&text);
+        *self.boundaries.borrow_mut() = boundaries;
+    }
+
+    fn coordinate_inside(&self, x: f32, y: f32) -> bool {
+        if x > self.pos_x && x < self.pos_x + self.width && y > self.pos_y && y < self.pos_y + self.height.get() {
+            return true;
+        }
+        false
+    }
+
+    // a line of text with a small margin
+    fn size(&self, gui: &Gui) -> (f32, f32) {
+        self.height.set(gui.font.line_spacing(gui.font_size) + 6.0);
+        return (self.width, self.height.get());
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn mouse_moved(&mut self, x: f32, _y: f32, hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        self.hovered = hovered;
+        if self.selecting {
+            self.cursor = self.index_at(x);
+        }
+    }
+
+    // the press places the cursor, dragging selects
+    fn mouse_pressed(&mut self, x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        self.cursor = self.index_at(x);
+        self.anchor = self.cursor;
+        self.selecting = true;
+        return true;
+    }
+
+    fn mouse_released(&mut self, _x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) {
+        self.selecting = false;
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    // takes every key while focused, so typing doesn't trigger the shortcuts of the app
+    fn key_pressed(&mut self, code: Key, shift: bool, ctrl: bool, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        let length: usize = self.length();
+        let (start, end) = self.selection();
+        match code {
+            Key::Left | Key::Right | Key::Home | Key::End => {
+                self.cursor = match code {
+                    Key::Left if start != end && !shift => start,
+                    Key::Right if start != end && !shift => end,
+                    Key::Left => self.cursor.saturating_sub(1),
+                    Key::Right => (self.cursor + 1).min(length),
+                    Key::Home => 0,
+                    _ => length,
+                };
+                if !shift {
+                    self.anchor = self.cursor;
+                }
+            }
+            Key::Backspace => self.delete(false),
+            Key::Delete => self.delete(true),
+            Key::Enter => self.submit(messages),
+            Key::A if ctrl => {
+                self.anchor = 0;
+                self.cursor = length;
+            }
+            Key::C if ctrl && start != end => clipboard::set_string(&self.selected_text()),
+            Key::X if ctrl && start != end => {
+                clipboard::set_string(&self.selected_text());
+                self.insert("");
+            }
+            Key::V if ctrl => self.paste(&clipboard::get_string()),
+            _ => (),
+        }
+        return true;
+    }
+
+    fn text_entered(&mut self, c: char, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        // backspace, enter and the ctrl shortcuts also arrive as characters
+        if !c.is_control() {
+            self.insert(&c.to_string());
+        }
+        return true;
+    }
+
+    // everything is selected on focus, leaving keeps a finished value and reverts anything else
+    fn set_focused(&mut self, focused: bool, messages: &mut Vec<Box<dyn Any>>) {
+        self.focused = focused;
+        if focused {
+            self.anchor = 0;
+            self.cursor = self.length();
+            return;
+        }
+        if self.mode.check(&self.text).0 {
+            self.submit(messages);
+        } else {
+            self.text = self.submitted.clone();
+        }
+        self.cursor = self.length();
+        self.anchor = self.cursor;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+// the open list of a dropdown shows at most this many options, the wheel scrolls through longer ones
+const MAX_VISIBLE_OPTIONS: usize = 8;
+
+// shows the selected option, a press opens the list of all options drawn above the other widgets
+pub struct Dropdown {
+    pos_x: f32,
+    pos_y: f32,
+    width: f32,
+    options: Vec<String>,
+    selected: usize,
+    open: bool,
+    hovered: bool,
+    // the option under the cursor while the list is open
+    hovered_option: Option<usize>,
+    // the first option the open list shows
+    scroll: usize,
+    // of the closed box and of every option, depends on the font
+    height: Cell<f32>,
+    on_change: Option<Handler<usize>>,
+}
+
+impl Dropdown {
+    pub fn create(gui: &mut Gui, id: &str, width: f32, options: Vec<String>) {
+        let dropdown = Box::new(Dropdown{pos_x: 0.0, pos_y: 0.0, width, options, selected: 0, open: false, hovered: false, hovered_option: None, scroll: 0, height: Cell::new(0.0), on_change: None});
+        gui.add(id, dropdown);
+    }
+
+    // sent with the index of the newly selected option
+    pub fn on_change<M: Any>(&mut self, handler: impl Fn(usize) -> M + 'static) {
+        self.on_change = Some(Box::new(move |index| Box::new(handler(index))));
+    }
+
+    // for loading values, doesn't send a message
+    pub fn set_options(&mut self, options: Vec<String>, selected: usize) {
+        self.options = options;
+        self.selected = selected.min(self.options.len().saturating_sub(1));
+        self.hovered_option = None;
+        self.scroll = 0;
+        self.scroll_to(self.selected);
+    }
+
+    #[allow(dead_code)]
+    pub fn selected(&self) -> usize {
+        return self.selected;
+    }
+
+    fn visible_options(&self) -> usize {
+        return self.options.len().min(MAX_VISIBLE_OPTIONS);
+    }
+
+    // moves the list as little as needed to show the option
+    fn scroll_to(&mut self, index: usize) {
+        self.scroll = self.scroll.min(index).max((index + 1).saturating_sub(self.visible_options()));
+    }
+
+    // the list opens below the box
+    fn option_at(&self, x: f32, y: f32) -> Option<usize> {
+        if !self.open || x <= self.pos_x || x >= self.pos_x + self.width || y <= self.pos_y + self.height.get() {
+            return None;
+        }
+        let row: usize = ((y - self.pos_y) / self.height.get()) as usize - 1;
+        if row >= self.visible_options() {
+            return None;
+        }
+        return Some(self.scroll + row).filter(|i| *i < self.options.len());
+    }
+
+    // the list starts at the selected option
+    fn toggle(&mut self) {
+        self.open = !self.open;
+        self.scroll_to(self.selected);
+    }
+
+    fn choose(&mut self, index: usize, messages: &mut Vec<Box<dyn Any>>) {
+        if index == self.selected || index >= self.options.len() {
+            return;
+        }
+        self.selected = index;
+        self.scroll_to(index);
+        if let Some(handler) = &self.on_change {
+            messages.push(handler(index));
+        }
+    }
+}
+
+impl GuiComponent for Dropdown {
+    fn render<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, target: &mut dyn sfml::graphics::RenderTarget, _states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, gui: &Gui) {
+        let height: f32 = self.height.get();
+        let mut b: RectangleShape = RectangleShape::new();
+        b.set_position(Vector2f::new(self.pos_x, self.pos_y));
+        b.set_size(Vector2f::new(self.width, height));
+        b.set_fill_color(gui.state_color(self.hovered, self.open));
+        b.set_outline_color(gui.colorscheme.sfml_color(ColorSchemeNames::Outline));
+        b.set_outline_thickness(1.0);
+        target.draw(&b);
+        let selected: &str = self.options.get(self.selected).map(|o| o.as_str()).unwrap_or("");
+        let mut text = Text::new(selected, &gui.font, gui.font_size);
+        text.set_position(Vector2f::new(self.pos_x + 4.0, self.pos_y + 3.0));
+        text.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Text));
+        target.draw(&text);
+        // a triangle pointing down, up while open
+        let mut arrow: CircleShape = CircleShape::new(6.0, 3);
+        arrow.set_origin(Vector2f::new(6.0, 6.0));
+        arrow.set_position(Vector2f::new(self.pos_x + self.width - 14.0, self.pos_y + height / 2.0));
+        arrow.set_rotation(if self.open { 0.0 } else { 180.0 });
+        arrow.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Text));
+        target.draw(&arrow);
+    }
+
+    fn render_overlay<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, target: &mut dyn sfml::graphics::RenderTarget, _states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, gui: &Gui) {
+        if !self.open {
+            return;
+        }
+        let height: f32 = self.height.get();
+        let visible: usize = self.visible_options();
+        for (i, option) in self.options.iter().enumerate().skip(self.scroll).take(visible) {
+            let y: f32 = self.pos_y + height * (i - self.scroll + 1) as f32;
+            let fill: ColorSchemeNames = match (self.hovered_option == Some(i), i == self.selected) {
+                (true, _) => ColorSchemeNames::Hover,
+                (false, true) => ColorSchemeNames::Selected,
+                _ => ColorSchemeNames::Fill,
+            };
+            let mut b: RectangleShape = RectangleShape::new();
+            b.set_position(Vector2f::new(self.pos_x, y));
+            b.set_size(Vector2f::new(self.width, height));
+            b.set_fill_color(gui.opaque(fill));
+            b.set_outline_color(gui.opaque(ColorSchemeNames::Outline));
+            b.set_outline_thickness(1.0);
+            target.draw(&b);
+            let mut text = Text::new(option, &gui.font, gui.font_size);
+            text.set_position(Vector2f::new(self.pos_x + 4.0, y + 3.0));
+            text.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Text));
+            target.draw(&text);
+        }
+        // a bar on the right shows which part of a longer list is shown
+        if self.options.len() > visible {
+            let track: f32 = height * visible as f32;
+            let mut bar: RectangleShape = RectangleShape::new();
+            bar.set_position(Vector2f::new(self.pos_x + self.width - 5.0, self.pos_y + height + track * self.scroll as f32 / self.options.len() as f32));
+            bar.set_size(Vector2f::new(3.0, track * visible as f32 / self.options.len() as f32));
+            bar.set_fill_color(gui.opaque(ColorSchemeNames::Outline));
+            target.draw(&bar);
+        }
+    }
+
+    fn coordinate_inside(&self, x: f32, y: f32) -> bool {
+        if x > self.pos_x && x < self.pos_x + self.width && y > self.pos_y && y < self.pos_y + self.height.get() {
+            return true;
+        }
+        false
+    }
+
+    fn overlay_inside(&self, x: f32, y: f32) -> bool {
+        return self.option_at(x, y).is_some();
+    }
+
+    // the closed box, the open list doesn't move other widgets
+    fn size(&self, gui: &Gui) -> (f32, f32) {
+        self.height.set(gui.font.line_spacing(gui.font_size) + 6.0);
+        return (self.width, self.height.get());
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn mouse_moved(&mut self, x: f32, y: f32, hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        self.hovered = hovered;
+        self.hovered_option = self.option_at(x, y);
+    }
+
+    // a press on the box opens or closes the list, one on an option picks it
+    fn mouse_pressed(&mut self, x: f32, y: f32, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        match self.option_at(x, y) {
+            Some(index) => {
+                self.choose(index, messages);
+                self.open = false;
+            }
+            None => self.toggle(),
+        }
+        return true;
+    }
+
+    fn mouse_scrolled(&mut self, x: f32, y: f32, delta: f32, _messages: &mut Vec<Box<dyn Any>>) {
+        if !self.open {
+            return;
+        }
+        let last: usize = self.options.len() - self.visible_options();
+        self.scroll = if delta > 0.0 { self.scroll.saturating_sub(1) } else { (self.scroll + 1).min(last) };
+        self.hovered_option = self.option_at(x, y);
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    // up and down pick the neighbouring option right away, space and enter open or close the list
+    fn key_pressed(&mut self, code: Key, _shift: bool, _ctrl: bool, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        match code {
+            Key::Up => self.choose(self.selected.saturating_sub(1), messages),
+            Key::Down => self.choose(self.selected + 1, messages),
+            Key::Space | Key::Enter => self.toggle(),
+            _ => return false,
+        }
+        return true;
+    }
+
+    // clicking anywhere else takes the focus and closes the list
+    fn set_focused(&mut self, focused: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        if !focused {
+            self.open = false;
+        }
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+// one of a few options, a column of round buttons with their names
+pub struct RadioGroup {
+    pos_x: f32,
+    pos_y: f32,
+    options: Vec<String>,
+    selected: usize,
+    hovered_option: Option<usize>,
+    // depend on the font and the option names, known once the layout measured them
+    row_height: Cell<f32>,
+    width: Cell<f32>,
+    on_change: Option<Handler<usize>>,
+}
+
+impl RadioGroup {
+    pub fn create(gui: &mut Gui, id: &str, options: Vec<String>) {
+        let group = Box::new(RadioGroup{pos_x: 0.0, pos_y: 0.0, options, selected: 0, hovered_option: None, row_height: Cell::new(0.0), width: Cell::new(0.0), on_change: None});
+        gui.add(id, group);
+    }
+
+    // sent with the index of the newly selected option
+    pub fn on_change<M: Any>(&mut self, handler: impl Fn(usize) -> M + 'static) {
+        self.on_change = Some(Box::new(move |index| Box::new(handler(index))));
+    }
+
+    // for loading values, doesn't send a message
+    pub fn set_selected(&mut self, selected: usize) {
+        self.selected = selected.min(self.options.len().saturating_sub(1));
+    }
+
+    fn option_at(&self, y: f32) -> Option<usize> {
+        if y <= self.pos_y {
+            return None;
+        }
+        let index: usize = ((y - self.pos_y) / self.row_height.get().max(1.0)) as usize;
+        return Some(index).filter(|i| *i < self.options.len());
+    }
+
+    fn choose(&mut self, index: usize, messages: &mut Vec<Box<dyn Any>>) {
+        if index == self.selected || index >= self.options.len() {
+            return;
+        }
+        self.selected = index;
+        if let Some(handler) = &self.on_change {
+            messages.push(handler(index));
+        }
+    }
+}
+
+impl GuiComponent for RadioGroup {
+    fn render<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, target: &mut dyn sfml::graphics::RenderTarget, _states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, gui: &Gui) {
+        let row: f32 = self.row_height.get();
+        for (i, option) in self.options.iter().enumerate() {
+            let y: f32 = self.pos_y + row * i as f32;
+            // green like a checked checkbox
+            let mut button: CircleShape = CircleShape::new(9.0, 20);
+            button.set_position(Vector2f::new(self.pos_x + 1.0, y + (row - 18.0) / 2.0));
+            button.set_fill_color(match i == self.selected {
+                true => Color::GREEN,
+                false => gui.state_color(self.hovered_option == Some(i), false),
+            });
+            button.set_outline_color(gui.colorscheme.sfml_color(ColorSchemeNames::Outline));
+            button.set_outline_thickness(1.0);
+            target.draw(&button);
+            let mut text = Text::new(option, &gui.font, gui.font_size);
+            text.set_position(Vector2f::new(self.pos_x + 30.0, y));
+            text.set_fill_color(gui.colorscheme.sfml_color(ColorSchemeNames::Text));
+            target.draw(&text);
+        }
+    }
+
+    fn coordinate_inside(&self, x: f32, y: f32) -> bool {
+        let height: f32 = self.row_height.get() * self.options.len() as f32;
+        if x > self.pos_x && x < self.pos_x + self.width.get() && y > self.pos_y && y < self.pos_y + height {
+            return true;
+        }
+        false
+    }
+
+    fn size(&self, gui: &Gui) -> (f32, f32) {
+        let widest: f32 = self.options.iter().map(|option| {
+            let text = Text::new(option, &gui.font, gui.font_size);
+            text.local_bounds().left + text.local_bounds().width
+        }).fold(0.0, f32::max);
+        self.row_height.set(gui.font.line_spacing(gui.font_size).max(20.0));
+        self.width.set(30.0 + widest);
+        return (self.width.get(), self.row_height.get() * self.options.len() as f32);
+    }
+
+    fn set_position(&mut self, x: f32, y: f32) {
+        self.pos_x = x;
+        self.pos_y = y;
+    }
+
+    fn mouse_moved(&mut self, _x: f32, y: f32, hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {
+        self.hovered_option = if hovered { self.option_at(y) } else { None };
+    }
+
+    fn mouse_pressed(&mut self, _x: f32, y: f32, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        if let Some(index) = self.option_at(y) {
+            self.choose(index, messages);
+        }
+        return true;
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+
+    fn key_pressed(&mut self, code: Key, _shift: bool, _ctrl: bool, messages: &mut Vec<Box<dyn Any>>) -> bool {
+        match code {
+            Key::Up | Key::Left => self.choose(self.selected.saturating_sub(1), messages),
+            Key::Down | Key::Right => self.choose(self.selected + 1, messages),
+            _ => return false,
+        }
+        return true;
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+// places its children with a layout and draws nothing itself
+pub struct Container {
+    layout: Layout,
+}
+
+impl Container {
+    pub fn create(gui: &mut Gui, id: &str, layout: Layout) {
+        let container = Box::new(Container{layout});
+        gui.add(id, container);
+    }
+}
+
+impl GuiComponent for Container {
+    fn render<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, _target: &mut dyn sfml::graphics::RenderTarget, _states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, _gui: &Gui) {}
+
+    fn coordinate_inside(&self, _x: f32, _y: f32) -> bool {
+        false
+    }
+
+    // measured from the children by the gui
+    fn size(&self, _gui: &Gui) -> (f32, f32) {
+        (0.0, 0.0)
+    }
+
+    // the gui keeps the position of every node
+    fn set_position(&mut self, _x: f32, _y: f32) {}
+
+    fn as_any(&self) -> &dyn Any {
+        return self;
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        return self;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // containers need no font, so a tree of them stands in for the gui
+    fn container(id: &str, children: Vec<Node>) -> Node {
+        let widget: Box<Container> = Box::new(Container { layout: Layout::Vertical { spacing: 0.0 } });
+        return Node { id: id.to_string(), widget, children, position: (0.0, 0.0) };
+    }
+
+    fn tree() -> Vec<Node> {
+        let row: Node = container("row", vec![container("a", Vec::new()), container("b", Vec::new()), container("c", Vec::new())]);
+        return vec![container("panel", vec![row, container("footer", Vec::new())]), container("other", Vec::new())];
+    }
+
+    fn ids(nodes: &[Node]) -> Vec<String> {
+        let mut ids: Vec<String> = Vec::new();
+        walk(nodes, &mut |node: &Node| ids.push(node.id.clone()));
+        return ids;
+    }
+
+    #[test]
+    fn remove_takes_the_subtree() {
+        let mut nodes: Vec<Node> = tree();
+        assert!(remove(&mut nodes, "row"));
+        assert_eq!(ids(&nodes), vec!["panel", "footer", "other"]);
+        assert!(find(&nodes, "a").is_none());
+        assert!(!remove(&mut nodes, "row"));
+        assert!(remove(&mut nodes, "panel"));
+        assert_eq!(ids(&nodes), vec!["other"]);
+    }
+
+    #[test]
+    fn reorder_among_siblings() {
+        let mut nodes: Vec<Node> = tree();
+        reorder(&mut nodes, "a", 1);
+        assert_eq!(ids(&find(&nodes, "row").unwrap().children), vec!["b", "a", "c"]);
+        // past the end is the end
+        reorder(&mut nodes, "b", 10);
+        assert_eq!(ids(&find(&nodes, "row").unwrap().children), vec!["a", "c", "b"]);
+        reorder(&mut nodes, "other", 0);
+        assert_eq!(ids(&nodes), vec!["other", "panel", "row", "a", "c", "b", "footer"]);
+        // a missing id changes nothing
+        reorder(&mut nodes, "missing", 0);
+        assert_eq!(ids(&nodes), vec!["other", "panel", "row", "a", "c", "b", "footer"]);
+    }
+
+    #[test]
+    fn get_finds_nested_widgets() {
+        let nodes: Vec<Node> = tree();
+        assert!(get::<Container>(&nodes, "b").layout == Layout::Vertical { spacing: 0.0 });
+    }
+
+    #[test]
+    #[should_panic(expected = "widget \"b\" is not a")]
+    fn get_of_another_type_panics() {
+        get::<Checkbox>(&tree(), "b");
+    }
+
+    #[test]
+    #[should_panic(expected = "gui has no widget \"missing\"")]
+    fn get_of_a_missing_id_panics() {
+        get::<Container>(&tree(), "missing");
+    }
+
+    #[test]
+    fn vertical_layout() {
+        let (offsets, size) = Layout::Vertical { spacing: 2.0 }.place(&[(10.0, 5.0), (30.0, 7.0), (20.0, 3.0)]);
+        assert_eq!(offsets, vec![(0.0, 0.0), (0.0, 7.0), (0.0, 16.0)]);
+        assert_eq!(size, (30.0, 19.0));
+    }
+
+    #[test]
+    fn horizontal_layout() {
+        // centered on the tallest child
+        let (offsets, size) = Layout::Horizontal { spacing: 2.0 }.place(&[(10.0, 5.0), (30.0, 7.0), (20.0, 3.0)]);
+        assert_eq!(offsets, vec![(0.0, 1.0), (12.0, 0.0), (44.0, 2.0)]);
+        assert_eq!(size, (64.0, 7.0));
+    }
+
+    #[test]
+    fn grid_layout() {
+        // the last row has a single cell, which still counts for the width of its column
+        let sizes: [(f32, f32); 5] = [(10.0, 4.0), (20.0, 6.0), (15.0, 2.0), (5.0, 8.0), (12.0, 3.0)];
+        let (offsets, size) = Layout::Grid { columns: 2, spacing: 1.0 }.place(&sizes);
+        assert_eq!(offsets, vec![(0.0, 1.0), (16.0, 0.0), (0.0, 10.0), (16.0, 7.0), (0.0, 16.0)]);
+        assert_eq!(size, (36.0, 19.0));
+        // more columns than children is one row
+        let (offsets, size) = Layout::Grid { columns: 5, spacing: 1.0 }.place(&sizes[..2]);
+        assert_eq!(offsets, vec![(0.0, 1.0), (11.0, 0.0)]);
+        assert_eq!(size, (31.0, 6.0));
+    }
+
+    #[test]
+    fn padding_layout() {
+        let (offsets, size) = Layout::Padding { left: 1.0, top: 2.0, right: 3.0, bottom: 4.0 }.place(&[(10.0, 5.0), (8.0, 9.0)]);
+        assert_eq!(offsets, vec![(1.0, 2.0), (1.0, 2.0)]);
+        assert_eq!(size, (14.0, 15.0));
+    }
+
+    #[test]
+    fn layouts_without_children() {
+        for layout in [Layout::Vertical { spacing: 2.0 }, Layout::Horizontal { spacing: 2.0 }, Layout::Grid { columns: 3, spacing: 2.0 }].iter() {
+            assert_eq!(layout.place(&[]), (Vec::new(), (0.0, 0.0)));
+        }
+        assert_eq!(Layout::Padding { left: 1.0, top: 2.0, right: 3.0, bottom: 4.0 }.place(&[]), (Vec::new(), (4.0, 6.0)));
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(InputMode::Integer.check("-42"), (true, true));
+        assert_eq!(InputMode::Integer.check("+"), (false, true));
+        assert_eq!(InputMode::Integer.check(""), (false, true));
+        assert_eq!(InputMode::Integer.check("1.5"), (false, false));
+        assert_eq!(InputMode::Integer.check("1e3"), (false, false));
+    }
+
+    #[test]
+    fn floats() {
+        for text in ["0.5", ".5", "5.", "-3.25", "+7"].iter() {
+            assert_eq!(InputMode::Float.check(text), (true, true), "{}", text);
+            assert!(text.parse::<f64>().is_ok());
+        }
+        assert_eq!(InputMode::Float.check("-."), (false, true));
+        assert_eq!(InputMode::Float.check("1.2.3"), (false, false));
+        assert_eq!(InputMode::Float.check("1e3"), (false, false));
+    }
+
+    #[test]
+    fn scientific() {
+        for text in ["1e3", "1.5E-3", "-.5e+10", "2"].iter() {
+            assert_eq!(InputMode::Scientific.check(text), (true, true), "{}", text);
+            assert!(text.parse::<f64>().is_ok());
+        }
+        assert_eq!(InputMode::Scientific.check("1e"), (false, true));
+        assert_eq!(InputMode::Scientific.check("1e-"), (false, true));
+        assert_eq!(InputMode::Scientific.check("e5"), (false, false));
+        assert_eq!(InputMode::Scientific.check("1e5e"), (false, false));
+    }
+
+    #[test]
+    fn text_takes_anything() {
+        assert_eq!(InputMode::Text.check("any text 1e"), (true, true));
+    }
+
+    fn dropdown(count: usize) -> Dropdown {
+        let options: Vec<String> = (0..count).map(|i| i.to_string()).collect();
+        return Dropdown { pos_x: 10.0, pos_y: 20.0, width: 100.0, options, selected: 0, open: true, hovered: false, hovered_option: None, scroll: 0, height: Cell::new(20.0), on_change: None };
+    }
+
+    #[test]
+    fn dropdown_options_below_the_box() {
+        let mut list: Dropdown = dropdown(3);
+        // the box itself and the edges of the list are no option
+        assert_eq!(list.option_at(50.0, 30.0), None);
+        assert_eq!(list.option_at(50.0, 40.0), None);
+        assert_eq!(list.option_at(50.0, 41.0), Some(0));
+        assert_eq!(list.option_at(50.0, 79.0), Some(1));
+        assert_eq!(list.option_at(50.0, 95.0), Some(2));
+        assert_eq!(list.option_at(50.0, 101.0), None);
+        assert_eq!(list.option_at(10.0, 45.0), None);
+        assert_eq!(list.option_at(110.0, 45.0), None);
+        list.open = false;
+        assert_eq!(list.option_at(50.0, 45.0), None);
+    }
+
+    #[test]
+    fn dropdown_scrolls_long_lists() {
+        let mut list: Dropdown = dropdown(12);
+        let row = |i: usize| -> f32 { 20.0 + 20.0 * (i + 1) as f32 + 5.0 };
+        assert_eq!(list.option_at(50.0, row(MAX_VISIBLE_OPTIONS - 1)), Some(MAX_VISIBLE_OPTIONS - 1));
+        assert_eq!(list.option_at(50.0, row(MAX_VISIBLE_OPTIONS)), None);
+        list.scroll = 4;
+        assert_eq!(list.option_at(50.0, row(0)), Some(4));
+        assert_eq!(list.option_at(50.0, row(MAX_VISIBLE_OPTIONS - 1)), Some(MAX_VISIBLE_OPTIONS + 3));
+        // only as far as needed to show an option
+        list.scroll_to(11);
+        assert_eq!(list.scroll, 12 - MAX_VISIBLE_OPTIONS);
+        list.scroll_to(6);
+        assert_eq!(list.scroll, 12 - MAX_VISIBLE_OPTIONS);
+        list.scroll_to(1);
+        assert_eq!(list.scroll, 1);
+        // the wheel stops at both ends
+        let mut messages: Vec<Box<dyn Any>> = Vec::new();
+        for _ in 0..20 {
+            list.mouse_scrolled(50.0, row(0), -1.0, &mut messages);
+        }
+        assert_eq!((list.scroll, list.hovered_option), (12 - MAX_VISIBLE_OPTIONS, Some(12 - MAX_VISIBLE_OPTIONS)));
+        for _ in 0..20 {
+            list.mouse_scrolled(50.0, row(0), 1.0, &mut messages);
+        }
+        assert_eq!(list.scroll, 0);
+        assert!(messages.is_empty());
+    }
+
+    #[test]
+    fn radio_group_rows() {
+        let group: RadioGroup = RadioGroup { pos_x: 0.0, pos_y: 10.0, options: vec!["a".to_string(), "b".to_string(), "c".to_string()], selected: 0, hovered_option: None, row_height: Cell::new(20.0), width: Cell::new(50.0), on_change: None };
+        assert_eq!(group.option_at(10.0), None);
+        assert_eq!(group.option_at(11.0), Some(0));
+        assert_eq!(group.option_at(29.0), Some(0));
+        assert_eq!(group.option_at(30.0), Some(1));
+        assert_eq!(group.option_at(69.0), Some(2));
+        assert_eq!(group.option_at(70.0), None);
+    }
+
+    // the cursor at the end, like after set_text
+    fn input(text: &str, mode: InputMode) -> TextInput {
+        let length: usize = text.chars().count();
+        return TextInput { pos_x: 0.0, pos_y: 0.0, width: 100.0, text: text.to_string(), mode, cursor: length, anchor: length, submitted: text.to_string(), boundaries: RefCell::new(Vec::new()), scroll: Cell::new(0.0), height: Cell::new(0.0), on_change: None, hovered: false, focused: false, selecting: false };
+    }
+
+    #[test]
+    fn insert_refuses_what_can_never_be_a_number() {
+        let mut field: TextInput = input("12", InputMode::Integer);
+        field.insert("a");
+        assert_eq!((field.text(), field.cursor), ("12", 2));
+        field.insert("3");
+        assert_eq!((field.text(), field.cursor), ("123", 3));
+        // a lone sign can still become one
+        field.anchor = 0;
+        field.insert("-");
+        assert_eq!((field.text(), field.cursor, field.anchor), ("-", 1, 1));
+        let mut field: TextInput = input("1.5", InputMode::Float);
+        field.insert(".");
+        assert_eq!(field.text(), "1.5");
+        field.cursor = 0;
+        field.anchor = 0;
+        field.insert("e");
+        assert_eq!(field.text(), "1.5");
+    }
+
+    #[test]
+    fn delete_without_a_selection() {
+        let mut field: TextInput = input("abc", InputMode::Text);
+        field.cursor = 1;
+        field.anchor = 1;
+        field.delete(false);
+        assert_eq!((field.text(), field.cursor), ("bc", 0));
+        field.delete(false);
+        assert_eq!((field.text(), field.cursor), ("bc", 0));
+        field.delete(true);
+        assert_eq!((field.text(), field.cursor), ("c", 0));
+        field.cursor = 1;
+        field.anchor = 1;
+        field.delete(true);
+        assert_eq!((field.text(), field.cursor), ("c", 1));
+    }
+
+    #[test]
+    fn delete_with_a_selection() {
+        // either direction removes the selection, not the character next to it
+        for (anchor, cursor, forward) in [(1, 4, true), (4, 1, false)].iter() {
+            let mut field: TextInput = input("hello", InputMode::Text);
+            field.anchor = *anchor;
+            field.cursor = *cursor;
+            field.delete(*forward);
+            assert_eq!((field.text(), field.cursor, field.anchor), ("ho", 1, 1));
+        }
+        // unless what is left can't be a number
+        let mut field: TextInput = input("1e5", InputMode::Scientific);
+        field.anchor = 0;
+        field.cursor = 1;
+        field.delete(false);
+        assert_eq!(field.text(), "1e5");
+    }
+
+    #[test]
+    fn byte_index_of_multibyte_characters() {
+        let mut field: TextInput = input("aé€😀b", InputMode::Text);
+        let indices: Vec<usize> = (0..7).map(|i| field.byte_index(i)).collect();
+        assert_eq!(indices, vec![0, 1, 3, 6, 10, 11, 11]);
+        field.anchor = 1;
+        field.cursor = 4;
+        assert_eq!(field.selected_text(), "é€😀");
+        field.cursor = 2;
+        field.anchor = 2;
+        field.insert("x");
+        assert_eq!((field.text(), field.cursor), ("aéx€😀b", 3));
+        field.delete(true);
+        assert_eq!(field.text(), "aéx😀b");
+    }
+
+    #[test]
+    fn paste_drops_control_characters() {
+        let mut field: TextInput = input("", InputMode::Text);
+        field.paste(" two\r\nlines\t ");
+        assert_eq!(field.text(), " twolines ");
+        let mut field: TextInput = input("", InputMode::Integer);
+        field.paste(" 42\n");
+        assert_eq!(field.text(), "42");
+        // trimming doesn't make anything else a number
+        field.paste("1 x");
+        assert_eq!(field.text(), "42");
+    }
+
+    #[test]
+    fn scrolling_follows_the_cursor() {
+        let offsets: Vec<f32> = (0..=10).map(|i| i as f32 * 10.0).collect();
+        let mut field: TextInput = input("0123456789", InputMode::Text);
+        assert_eq!(field.scroll_to_cursor(&offsets, 35.0), 65.0);
+        field.cursor = 0;
+        assert_eq!(field.scroll_to_cursor(&offsets, 35.0), 0.0);
+        // only as far as the cursor needs
+        field.cursor = 5;
+        assert_eq!(field.scroll_to_cursor(&offsets, 35.0), 15.0);
+        field.cursor = 3;
+        assert_eq!(field.scroll_to_cursor(&offsets, 35.0), 15.0);
+        // a text that fits doesn't move
+        let field: TextInput = input("01", InputMode::Text);
+        field.scroll.set(15.0);
+        assert_eq!(field.scroll_to_cursor(&offsets[..3], 35.0), 0.0);
+    }
+}
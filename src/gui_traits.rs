@@ -1,7 +1,51 @@
+use std::any::Any;
+
+use sfml::window::Key;
+
 use crate::gui::Gui;
 
-pub trait GuiComponent {
+pub trait GuiComponent: Any {
     fn render<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, target: &mut dyn sfml::graphics::RenderTarget, states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, gui: &Gui);
     fn coordinate_inside(&self, x: f32, y: f32) -> bool;
-    
-}
\ No newline at end of file
+
+    // popups are drawn after every widget and take the mouse before them
+    fn render_overlay<'a: 'shader, 'texture, 'shader, 'shader_texture>(&'a self, _target: &mut dyn sfml::graphics::RenderTarget, _states: &sfml::graphics::RenderStates<'texture, 'shader, 'shader_texture>, _gui: &Gui) {}
+    fn overlay_inside(&self, _x: f32, _y: f32) -> bool {
+        false
+    }
+
+    // width and height for the layout, which then moves the widget with set_position
+    fn size(&self, gui: &Gui) -> (f32, f32);
+    fn set_position(&mut self, x: f32, y: f32);
+
+    // mouse input routed by the gui, changes made by the user are reported by pushing the message of the widget's handler.
+    // every widget hears about moves, hovered is true for the one on top under the cursor
+    fn mouse_moved(&mut self, _x: f32, _y: f32, _hovered: bool, _messages: &mut Vec<Box<dyn Any>>) {}
+    // a left press on the widget, returning true captures the mouse until the release
+    fn mouse_pressed(&mut self, _x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        false
+    }
+    fn mouse_released(&mut self, _x: f32, _y: f32, _messages: &mut Vec<Box<dyn Any>>) {}
+    // the vertical wheel over the widget, delta is positive away from the user
+    fn mouse_scrolled(&mut self, _x: f32, _y: f32, _delta: f32, _messages: &mut Vec<Box<dyn Any>>) {}
+
+    // tab stops at focusable widgets, which then get the keys. returning true keeps the key from the app
+    fn focusable(&self) -> bool {
+        false
+    }
+    fn key_pressed(&mut self, _code: Key, _shift: bool, _ctrl: bool, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        false
+    }
+    // the release of a key the widget used
+    fn key_released(&mut self, _code: Key, _messages: &mut Vec<Box<dyn Any>>) {}
+    // a typed character, returning true keeps it from the app
+    fn text_entered(&mut self, _c: char, _messages: &mut Vec<Box<dyn Any>>) -> bool {
+        false
+    }
+    // the widget got or lost the focus
+    fn set_focused(&mut self, _focused: bool, _messages: &mut Vec<Box<dyn Any>>) {}
+
+    // for the typed lookups of the gui
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
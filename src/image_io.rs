@@ -0,0 +1,351 @@
+use std::{fs, io, path::Path};
+
+use crate::math::color;
+
+// formats for renders, everything but Png8 is written from the float frame before dithering
+#[derive(Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Png8,
+    Png16,
+    Pfm,
+    Exr,
+}
+
+impl ExportFormat {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ExportFormat::Png8 => "8 bit png",
+            ExportFormat::Png16 => "16 bit png",
+            ExportFormat::Pfm => "float pfm",
+            ExportFormat::Exr => "half float exr",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Png8 | ExportFormat::Png16 => "png",
+            ExportFormat::Pfm => "pfm",
+            ExportFormat::Exr => "exr",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            ExportFormat::Png8 => ExportFormat::Png16,
+            ExportFormat::Png16 => ExportFormat::Pfm,
+            ExportFormat::Pfm => ExportFormat::Exr,
+            ExportFormat::Exr => ExportFormat::Png8,
+        }
+    }
+}
+
+// frame is row major rgba in linear light like the renderer keeps it
+pub fn save<P>(filename: P, format: ExportFormat, width: usize, height: usize, frame: &[[f32; 4]]) -> io::Result<()>
+where P: AsRef<Path>, {
+    if frame.len() != width * height {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "frame doesn't match the image size"));
+    }
+    if width == 0 || height == 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "image has no pixels"));
+    }
+    let contents: Vec<u8> = match format {
+        ExportFormat::Png8 => return Err(io::Error::new(io::ErrorKind::InvalidInput, "8 bit png is written from the framebuffer")),
+        ExportFormat::Png16 => png16(width, height, frame),
+        ExportFormat::Pfm => pfm(width, height, frame),
+        ExportFormat::Exr => exr(width, height, frame),
+    };
+    return fs::write(filename, contents);
+}
+
+// 16 bit rgba png with the srgb curve, the image data goes into stored (uncompressed) deflate blocks
+fn png16(width: usize, height: usize, frame: &[[f32; 4]]) -> Vec<u8> {
+    let mut raw: Vec<u8> = Vec::with_capacity(height * (1 + width * 8));
+    for row in frame.chunks(width) {
+        // filter type none
+        raw.push(0);
+        for p in row {
+            for c in 0..4 {
+                let v: f64 = if c < 3 { color::linear_to_srgb(p[c].clamp(0.0, 1.0) as f64) } else { p[c].clamp(0.0, 1.0) as f64 };
+                raw.extend_from_slice(&((v * 65535.0).round() as u16).to_be_bytes());
+            }
+        }
+    }
+    let mut header: Vec<u8> = Vec::with_capacity(13);
+    header.extend_from_slice(&(width as u32).to_be_bytes());
+    header.extend_from_slice(&(height as u32).to_be_bytes());
+    // bit depth 16, color type rgba, deflate, adaptive filtering, no interlace
+    header.extend_from_slice(&[16, 6, 0, 0, 0]);
+
+    let mut png: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a];
+    png_chunk(&mut png, b"IHDR", &header);
+    // perceptual rendering intent
+    png_chunk(&mut png, b"sRGB", &[0]);
+    png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+    png_chunk(&mut png, b"IEND", &[]);
+    return png;
+}
+
+fn png_chunk(png: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start: usize = png.len();
+    png.extend_from_slice(kind);
+    png.extend_from_slice(data);
+    let crc: u32 = crc32(&png[start..]);
+    png.extend_from_slice(&crc.to_be_bytes());
+}
+
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    // deflate, 32k window, no preset dictionary, the check bits make the header a multiple of 31
+    let mut out: Vec<u8> = vec![0x78, 0x01];
+    let mut blocks = data.chunks(u16::MAX as usize).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(if blocks.peek().is_none() { 1 } else { 0 });
+        let len: u16 = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    return out;
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc: u32 = 0xffff_ffff;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 { 0xedb8_8320 ^ (crc >> 1) } else { crc >> 1 };
+        }
+    }
+    return !crc;
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b): (u32, u32) = (1, 0);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    return b << 16 | a;
+}
+
+// portable float map, linear rgb, a negative scale means little endian and rows go bottom to top
+fn pfm(width: usize, height: usize, frame: &[[f32; 4]]) -> Vec<u8> {
+    let mut out: Vec<u8> = format!("PF\n{} {}\n-1.0\n", width, height).into_bytes();
+    for row in frame.chunks(width).rev() {
+        for p in row {
+            (0..3).for_each(|c| out.extend_from_slice(&p[c].to_le_bytes()));
+        }
+    }
+    return out;
+}
+
+// single part scanline openexr with uncompressed half float b, g and r channels in linear light
+fn exr(width: usize, height: usize, frame: &[[f32; 4]]) -> Vec<u8> {
+    let mut out: Vec<u8> = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    let mut channels: Vec<u8> = Vec::new();
+    // channels are sorted by name, pixel type 1 is half
+    for name in ["B", "G", "R"].iter() {
+        channels.extend_from_slice(name.as_bytes());
+        channels.push(0);
+        channels.extend_from_slice(&1_i32.to_le_bytes());
+        channels.extend_from_slice(&[0, 0, 0, 0]);
+        channels.extend_from_slice(&1_i32.to_le_bytes());
+        channels.extend_from_slice(&1_i32.to_le_bytes());
+    }
+    channels.push(0);
+    let mut window: Vec<u8> = Vec::new();
+    for v in [0, 0, width as i32 - 1, height as i32 - 1].iter() {
+        window.extend_from_slice(&v.to_le_bytes());
+    }
+    exr_attribute(&mut out, "channels", "chlist", &channels);
+    exr_attribute(&mut out, "compression", "compression", &[0]);
+    exr_attribute(&mut out, "dataWindow", "box2i", &window);
+    exr_attribute(&mut out, "displayWindow", "box2i", &window);
+    exr_attribute(&mut out, "lineOrder", "lineOrder", &[0]);
+    exr_attribute(&mut out, "pixelAspectRatio", "float", &1.0_f32.to_le_bytes());
+    exr_attribute(&mut out, "screenWindowCenter", "v2f", &[0; 8]);
+    exr_attribute(&mut out, "screenWindowWidth", "float", &1.0_f32.to_le_bytes());
+    out.push(0);
+
+    // one scanline per block, the offset table points at every block
+    let line_size: usize = width * 3 * 2;
+    let first: usize = out.len() + height * 8;
+    for y in 0..height {
+        out.extend_from_slice(&((first + y * (8 + line_size)) as u64).to_le_bytes());
+    }
+    for (y, row) in frame.chunks(width).enumerate() {
+        out.extend_from_slice(&(y as i32).to_le_bytes());
+        out.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for c in (0..3).rev() {
+            row.iter().for_each(|p| out.extend_from_slice(&half(p[c]).to_le_bytes()));
+        }
+    }
+    return out;
+}
+
+fn exr_attribute(out: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    out.extend_from_slice(name.as_bytes());
+    out.push(0);
+    out.extend_from_slice(kind.as_bytes());
+    out.push(0);
+    out.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    out.extend_from_slice(value);
+}
+
+// f32 to ieee half, rounds to nearest, too large values become infinity and tiny ones subnormals or zero
+fn half(v: f32) -> u16 {
+    let bits: u32 = v.to_bits();
+    let sign: u16 = ((bits >> 16) & 0x8000) as u16;
+    let exponent: i32 = ((bits >> 23) & 0xff) as i32;
+    let mantissa: u32 = bits & 0x7f_ffff;
+    if exponent == 0xff {
+        // infinity stays infinity, nan keeps a mantissa bit
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+    let exponent: i32 = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        if exponent < -10 {
+            return sign;
+        }
+        let mantissa: u32 = mantissa | 0x80_0000;
+        let shift: u32 = (14 - exponent) as u32;
+        let rounded: u32 = (mantissa + (1 << (shift - 1))) >> shift;
+        return sign | rounded as u16;
+    }
+    let rounded: u32 = ((exponent as u32) << 10 | mantissa >> 13) + ((mantissa >> 12) & 1);
+    return sign | rounded as u16;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use super::*;
+
+    #[test]
+    fn half_known_values() {
+        assert_eq!(half(0.0), 0x0000);
+        assert_eq!(half(-0.0), 0x8000);
+        assert_eq!(half(1.0), 0x3c00);
+        assert_eq!(half(-2.0), 0xc000);
+        assert_eq!(half(0.5), 0x3800);
+        assert_eq!(half(65504.0), 0x7bff);
+    }
+
+    #[test]
+    fn half_rounds_to_nearest() {
+        // a quarter and three quarters of a step above one
+        assert_eq!(half(1.0 + 2.0_f32.powi(-12)), 0x3c00);
+        assert_eq!(half(1.0 + 3.0 * 2.0_f32.powi(-12)), 0x3c01);
+        // rounding can carry into the exponent
+        assert_eq!(half(2.0 - 2.0_f32.powi(-13)), 0x4000);
+    }
+
+    #[test]
+    fn half_subnormals() {
+        assert_eq!(half(2.0_f32.powi(-14)), 0x0400);
+        assert_eq!(half(2.0_f32.powi(-15)), 0x0200);
+        assert_eq!(half(2.0_f32.powi(-24)), 0x0001);
+        assert_eq!(half(-3.0 * 2.0_f32.powi(-24)), 0x8003);
+        assert_eq!(half(2.0_f32.powi(-26)), 0x0000);
+        // the largest subnormal rounds up to the smallest normal
+        assert_eq!(half(2.0_f32.powi(-14) - 2.0_f32.powi(-26)), 0x0400);
+    }
+
+    #[test]
+    fn half_infinity_and_nan() {
+        assert_eq!(half(f32::INFINITY), 0x7c00);
+        assert_eq!(half(f32::NEG_INFINITY), 0xfc00);
+        assert_eq!(half(1e6), 0x7c00);
+        assert_eq!(half(-1e6), 0xfc00);
+        // past the largest half it rounds to infinity
+        assert_eq!(half(65520.0), 0x7c00);
+        let nan: u16 = half(f32::NAN);
+        assert!(nan & 0x7c00 == 0x7c00 && nan & 0x3ff != 0);
+    }
+
+    #[test]
+    fn checksums_match_known_vectors() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b""), 1);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+    }
+
+    #[test]
+    fn zlib_stored_blocks() {
+        let empty: Vec<u8> = zlib_stored(&[]);
+        assert_eq!(empty, vec![0x78, 0x01, 1, 0, 0, 0xff, 0xff, 0, 0, 0, 1]);
+        assert_eq!((0x78 * 256 + 0x01) % 31, 0);
+        // a block can only hold 65535 bytes
+        let data: Vec<u8> = vec![7; 70000];
+        let out: Vec<u8> = zlib_stored(&data);
+        assert_eq!(&out[2..7], &[0, 0xff, 0xff, 0, 0]);
+        assert_eq!(&out[7 + 65535..7 + 65535 + 5], &[1, 0x71, 0x11, 0x8e, 0xee]);
+        assert_eq!(out.len(), 2 + 5 + 65535 + 5 + 4465 + 4);
+    }
+
+    #[test]
+    fn png_header() {
+        let frame: Vec<[f32; 4]> = vec![[1.0, 0.0, 0.5, 1.0]; 3 * 2];
+        let png: Vec<u8> = png16(3, 2, &frame);
+        assert_eq!(&png[..8], &[0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a]);
+        assert_eq!(&png[8..16], &[0, 0, 0, 13, b'I', b'H', b'D', b'R']);
+        assert_eq!(&png[16..29], &[0, 0, 0, 3, 0, 0, 0, 2, 16, 6, 0, 0, 0]);
+        assert_eq!(&png[29..33], &crc32(&png[12..29]).to_be_bytes());
+        assert_eq!(&png[png.len() - 12..], &[0, 0, 0, 0, b'I', b'E', b'N', b'D', 0xae, 0x42, 0x60, 0x82]);
+        // a filter byte then big endian srgb encoded channels, after the zlib header and the block header
+        let idat: usize = png.windows(4).position(|w| w == b"IDAT").unwrap();
+        let raw: &[u8] = &png[idat + 4 + 7..];
+        let blue: [u8; 2] = ((color::linear_to_srgb(0.5) * 65535.0).round() as u16).to_be_bytes();
+        assert_eq!(&raw[..9], &[0, 0xff, 0xff, 0, 0, blue[0], blue[1], 0xff, 0xff]);
+    }
+
+    #[test]
+    fn exr_header() {
+        let frame: Vec<[f32; 4]> = vec![[1.0, 0.5, 0.0, 1.0]; 2 * 3];
+        let exr: Vec<u8> = exr(2, 3, &frame);
+        assert_eq!(&exr[..8], &[0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0]);
+        assert_eq!(&exr[8..25], b"channels\0chlist\0\x37");
+        // every offset points at a block starting with its scanline and the line size
+        let header_end: usize = exr.len() - 3 * (8 + 2 * 3 * 2) - 3 * 8;
+        for y in 0..3 {
+            let at: usize = header_end + y * 8;
+            let offset: usize = u64::from_le_bytes(<[u8; 8]>::try_from(&exr[at..at + 8]).unwrap()) as usize;
+            assert_eq!(&exr[offset..offset + 8], &[y as u8, 0, 0, 0, 12, 0, 0, 0]);
+            // b, g and r channels for both pixels
+            let halves: Vec<u16> = exr[offset + 8..offset + 20].chunks(2).map(|h| u16::from_le_bytes([h[0], h[1]])).collect();
+            assert_eq!(halves, vec![0, 0, 0x3800, 0x3800, 0x3c00, 0x3c00]);
+        }
+    }
+
+    #[test]
+    fn pfm_header() {
+        let frame: Vec<[f32; 4]> = vec![[0.25, 0.0, 0.0, 1.0], [0.0, 0.0, 0.0, 1.0]];
+        let pfm: Vec<u8> = pfm(1, 2, &frame);
+        assert!(pfm.starts_with(b"PF\n1 2\n-1.0\n"));
+        // bottom row first
+        assert_eq!(&pfm[pfm.len() - 12..pfm.len() - 8], &0.25_f32.to_le_bytes());
+    }
+
+    #[test]
+    fn empty_images_are_refused() {
+        // nothing is written, so the file name doesn't matter
+        let file: std::path::PathBuf = std::env::temp_dir().join(format!("mandelbrot-test-{}-empty", std::process::id()));
+        for format in [ExportFormat::Png16, ExportFormat::Pfm, ExportFormat::Exr].iter() {
+            for (width, height) in [(0, 0), (0, 5), (5, 0)].iter() {
+                let error: io::Error = save(&file, *format, *width, *height, &[]).unwrap_err();
+                assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+            }
+        }
+        assert!(!file.exists());
+    }
+}
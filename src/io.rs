@@ -1,5 +1,7 @@
 pub mod io {
-    use std::{path::Path, fs::File, io::{self, BufRead}};
+    use std::{path::Path, fs::{self, File}, io::{self, BufRead}};
+
+    use yaml_rust::{Yaml, YamlLoader, YamlEmitter};
 
     #[allow(dead_code)]
     pub fn read_lines<P>(filename: P) -> io::Result<io::Lines<io::BufReader<File>>>
@@ -7,4 +9,26 @@ pub mod io {
         let file = File::open(filename)?;
         Ok(io::BufReader::new(file).lines())
     }
+
+    // returns the first document of a yaml file, None if it can't be read or parsed
+    pub fn read_yaml<P>(filename: P) -> Option<Yaml>
+    where P: AsRef<Path>, {
+        let contents: String = fs::read_to_string(filename).ok()?;
+        let mut docs: Vec<Yaml> = YamlLoader::load_from_str(&contents).ok()?;
+        if docs.is_empty() {
+            return None;
+        }
+        return Some(docs.remove(0));
+    }
+
+    pub fn write_yaml<P>(filename: P, yaml: &Yaml) -> io::Result<()>
+    where P: AsRef<Path>, {
+        let mut contents: String = String::new();
+        match YamlEmitter::new(&mut contents).dump(yaml) {
+            Ok(_) => (),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{:?}", e))),
+        };
+        contents.push('\n');
+        return fs::write(filename, contents);
+    }
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+#[derive(Clone, Copy)]
+pub struct Light {
+    pub enabled: bool,
+    pub angle: f64,
+    pub height: f64,
+    pub specular: f64,
+}
+
+impl Light {
+    pub fn new(angle: f64, height: f64, specular: f64) -> Self {
+        return Light { enabled: false, angle, height, specular };
+    }
+
+    // angle is the direction in degrees, height goes from 0 (at the horizon) to 1 (straight above)
+    fn direction(&self) -> (f64, f64, f64) {
+        let azimuth: f64 = self.angle.to_radians();
+        let elevation: f64 = self.height.clamp(0.0, 1.0) * std::f64::consts::FRAC_PI_2;
+        return (azimuth.cos() * elevation.cos(), azimuth.sin() * elevation.cos(), elevation.sin());
+    }
+
+    // returns (diffuse, specular) for the pixel at x, y of a row major height map
+    pub fn shade(&self, heights: &[f64], width: i32, height: i32, x: i32, y: i32) -> (f64, f64) {
+        let at = |x: i32, y: i32| -> f64 {
+            heights[(y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize]
+        };
+        let depth: f64 = 4.0;
+        let dx: f64 = (at(x + 1, y) - at(x - 1, y)) / 2.0;
+        let dy: f64 = (at(x, y + 1) - at(x, y - 1)) / 2.0;
+        let normal: (f64, f64, f64) = normalize((-dx * depth, -dy * depth, 1.0));
+        let light: (f64, f64, f64) = self.direction();
+
+        let ambient: f64 = 0.2;
+        let diffuse: f64 = ambient + (1.0 - ambient) * dot(normal, light).max(0.0);
+        let half: (f64, f64, f64) = normalize((light.0, light.1, light.2 + 1.0));
+        let specular: f64 = self.specular * dot(normal, half).max(0.0).powf(32.0);
+        return (diffuse, specular);
+    }
+}
+
+fn dot(a: (f64, f64, f64), b: (f64, f64, f64)) -> f64 {
+    return a.0 * b.0 + a.1 * b.1 + a.2 * b.2;
+}
+
+fn normalize(v: (f64, f64, f64)) -> (f64, f64, f64) {
+    let len: f64 = dot(v, v).sqrt();
+    if len == 0.0 {
+        return (0.0, 0.0, 1.0);
+    }
+    return (v.0 / len, v.1 / len, v.2 / len);
+}
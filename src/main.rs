@@ -4,6 +4,7 @@ use application_manager::gman::Gm;
 extern crate sfml;
 extern crate num;
 extern crate yaml_rust;
+extern crate rand;
 
 mod application_manager;
 mod io;
@@ -13,6 +14,16 @@ mod math;
 mod gui;
 mod gui_traits;
 mod colorscheme;
+mod lighting;
+mod coloring;
+mod gradient;
+mod palette_io;
+mod palette_generator;
+mod dither;
+mod image_io;
+mod color_vision;
+mod post;
+mod watcher;
 
 fn main() {
     let mut g_manager: Gm = Gm::new();
@@ -1,15 +1,36 @@
-use std::{convert::TryInto, thread::JoinHandle};
+use std::{convert::TryInto, io, path::Path, thread::JoinHandle, sync::{Arc, Mutex}};
 use std::thread;
 
 use num::{Complex, complex::ComplexFloat};
 use sfml::{graphics::{Drawable, Sprite, Image, Texture, Rect, Color}, SfBox, system::Vector2i};
 
-use crate::math::math;
+use crate::{coloring::{self, ColorSettings, Colorizer, Antialiasing, Histogram, Transfer, TransferFunction}, dither::{self, Dithering}, image_io::{self, ExportFormat}, color_vision::Deficiency, post::{self, PostSettings}};
+
+const MAX_ITER: i32 = 80;
+const MAX_WORKER: i32 = 8;
+// largest difference of a color channel to a neighbor before adaptive antialiasing supersamples a pixel
+const ADAPTIVE_THRESHOLD: i32 = 24;
 
 pub struct Mandelbrot {
     pixels: sfml::graphics::Image,
     tex: SfBox<Texture>,
-    results: Vec<Vec<(i32, i32, (f64, Complex<f64>))>>,
+    // row major, one entry per pixel
+    results: Arc<Vec<(f64, Complex<f64>)>>,
+    // these only depend on the results, computing them once keeps recoloring cheap enough for color cycling
+    heights: Arc<Vec<f64>>,
+    histogram: Arc<Histogram>,
+    accumulation: Arc<Mutex<Accumulation>>,
+    generation: u64,
+}
+
+// running sum of the progressive refinement samples in linear light, generation tells
+// which set_color call the samples belong to
+struct Accumulation {
+    generation: u64,
+    samples: u32,
+    sums: Vec<[f32; 4]>,
+    // applied to the average, not to each sample
+    post: PostSettings,
 }
 
 impl Mandelbrot {
@@ -24,9 +45,9 @@ impl Mandelbrot {
             Err(_) => panic!("error loading texture from image"),
         };
         
-        let max_worker: i32 = 8;
+        let max_worker: i32 = MAX_WORKER;
         let mut workers: Vec<thread::JoinHandle<Vec<(i32, i32, (f64, Complex<f64>))>>> = Vec::new();
-        let mut results: Vec<Vec<(i32, i32, (f64, Complex<f64>))>> = Vec::new();
+        let mut results: Vec<(f64, Complex<f64>)> = vec![(MAX_ITER as f64, Complex::new(0.0, 0.0)); (size_x * size_y) as usize];
 
         for i in 0..max_worker {
             workers.push(thread::spawn(move || {
@@ -34,8 +55,8 @@ impl Mandelbrot {
                 let mut results: Vec<(i32, i32, (f64, Complex<f64>))> = Vec::new();
                 for x in (size_x / max_worker) * i..max_x {
                     for y in 0..size_y {
-                        let max_iter: i32 = 80;
-                        let c: Complex<f64> = num::complex::Complex::new(-2.0 + (x as f64 / size_x as f64) * (1.0 - -2.0), -1.0 + (y as f64 / size_y as f64) * (1.0 - -1.0));
+                        let max_iter: i32 = MAX_ITER;
+                        let c: Complex<f64> = Mandelbrot::complex_at(x as f64, y as f64, size_x, size_y);
         
                         let n: (f64, Complex<f64>) = Mandelbrot::run_mandelbrot(max_iter, c);
                         results.push((x, y, n));
@@ -45,51 +66,34 @@ impl Mandelbrot {
             })) 
         }
         
-        workers.into_iter().for_each(|worker| results.push(worker.join().unwrap()));
-
-        for i in &results {
-            for j in i {
-                let max_iter: i32 = 80;
-                let x: i32 = j.0;
-                let y: i32 = j.1;
-                let n: (f64, Complex<f64>) = j.2;
-                
-                let mut rgb: (i32, i32, i32) = (0, 0, 0);
-                if n.0 as i32 != max_iter {
-                    rgb = Self::map_color(n.0, n.1.re(), n.1.im(), 90.0, 70.0, 10.0);
-                }
-                //let color: Color = Color::rgba(0, 0, 0, (255.0 - n.0 * 255.0 / max_iter as f64) as u8);
-                /*let mut rgb: (u8, u8, u8) = (Color::BLACK.r, Color::BLACK.g, Color::BLACK.b);
-                if n < max_iter as f64 && n > 0.0 {
-                    let l: i32 = (n % 16.0) as i32;
-                    rgb = match l {
-                    0 => (66, 30, 15),
-                    1 => (25, 7, 26),
-                    2 => (9, 1, 47),
-                    3 => (4, 4, 73),
-                    4 => (0, 7, 100),
-                    5 => (12, 44, 138),
-                    6 => (24, 82, 177),
-                    7 => (57, 125, 209),
-                    8 => (134, 181, 229),
-                    9 => (211, 236, 248),
-                    10 => (241, 233, 191),
-                    11 => (248, 201, 0),
-                    12 => (255, 170, 0),
-                    13 => (204, 128, 0),
-                    14 => (153, 87, 0),
-                    15 => (106, 52, 3),
-                    _ => (66, 30, 15),
-                    }
-                }*/
-                let color: Color = Color::rgb(rgb.0 as u8, rgb.1 as u8, rgb.2 as u8);
-                unsafe {
-                    t.set_pixel(x as u32, y as u32, color);
-                }
+        workers.into_iter().for_each(|worker| {
+            for (x, y, n) in worker.join().unwrap() {
+                results[(y * size_x + x) as usize] = n;
+            }
+        });
+
+        for (i, n) in results.iter().enumerate() {
+            let max_iter: i32 = MAX_ITER;
+            let x: i32 = i as i32 % size_x;
+            let y: i32 = i as i32 / size_x;
+            
+            let mut rgb: (u8, u8, u8) = (0, 0, 0);
+            if n.0 as i32 != max_iter {
+                rgb = coloring::hue_color(Transfer::new(TransferFunction::Linear, 10.0 / 360.0, 0.0).apply(n.0), 90.0, 70.0);
+            }
+            let color: Color = Color::rgb(rgb.0, rgb.1, rgb.2);
+            unsafe {
+                t.set_pixel(x as u32, y as u32, color);
             }
         }
 
-        return Mandelbrot { pixels: t, tex, results};
+        // the smooth iteration count doubles as a height map for the lighting
+        let heights: Vec<f64> = results.iter().map(|(n, _)| n.max(1.0).ln()).collect();
+        let counts: Vec<f64> = results.iter().map(|(n, _)| *n).filter(|n| *n < MAX_ITER as f64).collect();
+        let histogram: Histogram = Histogram::new(&counts, 4096);
+
+        let accumulation: Accumulation = Accumulation { generation: 0, samples: 0, sums: Vec::new(), post: PostSettings::none() };
+        return Mandelbrot { pixels: t, tex, results: Arc::new(results), heights: Arc::new(heights), histogram: Arc::new(histogram), accumulation: Arc::new(Mutex::new(accumulation)), generation: 0 };
     }
 
     pub fn set_pixels(&mut self, pixels: Vec<u8>) {
@@ -101,50 +105,144 @@ impl Mandelbrot {
         }
     }
 
-    pub fn set_color(&mut self, value: f32, saturation: f32, modifier: f64, do_grayscale: bool) -> JoinHandle<Vec<u8>> {
-        let raw_pixels = self.pixels.pixel_data();
-        let mut pixels: Vec<u8> = Vec::with_capacity(self.pixels.size().x as usize * self.pixels.size().y as usize * 4);
-        for i in 0..pixels.capacity() {
-            pixels.push(raw_pixels[i]);
-        }
+    pub fn set_color(&mut self, settings: ColorSettings) -> JoinHandle<Vec<u8>> {
         let width: i32 = self.pixels.size().x as i32;
-        let results = self.results.clone();
+        let height: i32 = self.pixels.size().y as i32;
+        let results = Arc::clone(&self.results);
+        let colorizer: Colorizer = Colorizer::new(settings, Arc::clone(&self.heights), Arc::clone(&self.histogram), width, height, MAX_ITER);
+        let accumulation = Arc::clone(&self.accumulation);
+        self.generation += 1;
+        let generation: u64 = self.generation;
         let t: JoinHandle<Vec<u8>> = thread::spawn(move || {
-            for i in 0..results.len() {
-                for j in 0..results[i].len() {
-                    let (x, y, (n, z)) = results[i][j];
-                    if !do_grayscale {
-                        let (r, g, b) = Mandelbrot::map_color(n, z.re, z.im, value, saturation, modifier);
-                        Mandelbrot::set_pixel(x, y, Color::rgb(r.try_into().unwrap(), g.try_into().unwrap(), b.try_into().unwrap()), &mut pixels, width);
-                    } else {
-                        let color: Color = Color::rgba(0, 0, 0, (255.0 - n * 255.0 / 80.0 as f64) as u8);
-                        Mandelbrot::set_pixel(x, y, color, &mut pixels, width);
+            // colors stay in floats until the output stage
+            let mut frame: Vec<[f32; 4]> = vec![[0.0; 4]; (width * height) as usize];
+            let samples: u32 = match colorizer.settings().antialiasing {
+                Antialiasing::Grid(n) | Antialiasing::Adaptive(n) => n.max(1),
+                Antialiasing::Off => 1,
+            };
+            let supersample_everything: bool = matches!(colorizer.settings().antialiasing, Antialiasing::Grid(_));
+            Mandelbrot::for_each_band(&mut frame, width, height, |i, pixel| {
+                *pixel = if supersample_everything && samples > 1 {
+                    Mandelbrot::supersample(&colorizer, i, width, height, samples)
+                } else {
+                    colorizer.color(results[i].0, i)
+                };
+            });
+            // adaptive antialiasing only resamples pixels on edges of the single sample image
+            if let Antialiasing::Adaptive(_) = colorizer.settings().antialiasing {
+                let base: Vec<u8> = dither::quantize(&frame, width as usize, Dithering::Off);
+                let differs = |i: usize, j: usize| -> bool {
+                    (0..4).any(|c| (base[i * 4 + c] as i32 - base[j * 4 + c] as i32).abs() > ADAPTIVE_THRESHOLD)
+                };
+                Mandelbrot::for_each_band(&mut frame, width, height, |i, pixel| {
+                    let (x, y) = (i as i32 % width, i as i32 / width);
+                    let edge: bool = (x > 0 && differs(i, i - 1)) || (x + 1 < width && differs(i, i + 1))
+                        || (y > 0 && differs(i, i - width as usize)) || (y + 1 < height && differs(i, i + width as usize));
+                    if edge && samples > 1 {
+                        *pixel = Mandelbrot::supersample(&colorizer, i, width, height, samples);
                     }
-                }
+                });
+            }
+            let pixels: Vec<u8> = Mandelbrot::output(&frame, width, colorizer.settings());
+            // the finished frame is the first sample of the progressive refinement. the app doesn't wait for
+            // an older set_color before starting a new one, a slower older frame must not replace a newer one
+            let mut accumulation = accumulation.lock().unwrap();
+            if generation >= accumulation.generation {
+                accumulation.generation = generation;
+                accumulation.samples = 1;
+                accumulation.sums = frame;
+                accumulation.post = colorizer.settings().post;
             }
             return pixels
         });
         t
     }
 
-    fn set_pixel(x: i32, y: i32, color: Color, pixels: &mut Vec<u8>, width: i32) {
-        pixels[(((y * width as i32 + x) * 4) + 0) as usize] = color.r;
-        pixels[(((y * width as i32 + x) * 4) + 1) as usize] = color.g;
-        pixels[(((y * width as i32 + x) * 4) + 2) as usize] = color.b;
-        pixels[(((y * width as i32 + x) * 4) + 3) as usize] = color.a;
+    // adds one jittered sample to every pixel of the last set_color frame and returns the average,
+    // meant to be called repeatedly while the view stays the same
+    pub fn refine(&mut self, settings: ColorSettings, pass: u32) -> JoinHandle<Vec<u8>> {
+        let width: i32 = self.pixels.size().x as i32;
+        let height: i32 = self.pixels.size().y as i32;
+        let colorizer: Colorizer = Colorizer::new(settings, Arc::clone(&self.heights), Arc::clone(&self.histogram), width, height, MAX_ITER);
+        let accumulation = Arc::clone(&self.accumulation);
+        let generation: u64 = self.generation;
+        let t: JoinHandle<Vec<u8>> = thread::spawn(move || {
+            // r2 low discrepancy sequence, successive passes cover the pixel evenly
+            let jitter_x: f64 = (0.5 + pass as f64 * 0.7548776662466927).fract() - 0.5;
+            let jitter_y: f64 = (0.5 + pass as f64 * 0.5698402909980532).fract() - 0.5;
+            // the lock is only held to copy the sums in and out, an export can read the frame during the pass
+            let (mut sums, mut samples, add_sample) = {
+                let accumulation = accumulation.lock().unwrap();
+                let add_sample: bool = accumulation.generation == generation && accumulation.sums.len() == (width * height) as usize;
+                (accumulation.sums.clone(), accumulation.samples, add_sample)
+            };
+            if add_sample {
+                Mandelbrot::for_each_band(&mut sums, width, height, |i, sum| {
+                    let c: Complex<f64> = Mandelbrot::complex_at((i as i32 % width) as f64 + jitter_x, (i as i32 / width) as f64 + jitter_y, width, height);
+                    let color: [f32; 4] = colorizer.color(Mandelbrot::run_mandelbrot(MAX_ITER, c).0, i);
+                    (0..4).for_each(|c| sum[c] += color[c]);
+                });
+                samples += 1;
+            }
+            let average: f32 = samples.max(1) as f32;
+            let frame: Vec<[f32; 4]> = sums.iter().map(|sum| sum.map(|v| v / average)).collect();
+            if add_sample {
+                let mut accumulation = accumulation.lock().unwrap();
+                // a set_color that finished during the pass started over, its frame is kept
+                if accumulation.generation == generation && accumulation.samples + 1 == samples {
+                    accumulation.sums = sums;
+                    accumulation.samples = samples;
+                }
+            }
+            return Mandelbrot::output(&frame, width, colorizer.settings())
+        });
+        t
+    }
+
+    // the output stage from the float frame to the shown 8 bit image: post effects, deficiency preview, dithering
+    fn output(frame: &[[f32; 4]], width: i32, settings: &ColorSettings) -> Vec<u8> {
+        let mut frame: Vec<[f32; 4]> = post::apply(frame, width as usize, &settings.post);
+        if settings.deficiency != Deficiency::Normal {
+            frame.iter_mut().for_each(|p| *p = settings.deficiency.simulate_pixel(*p));
+        }
+        return dither::quantize(&frame, width as usize, settings.dithering);
     }
 
-    fn map_color(di: f64, r: f64, c: f64, saturation: f32, value: f32, modifier: f64) -> (i32, i32, i32) {
-        let mut hue: f64;
-
-        let _zn: f64 = (r + c).sqrt();
-        hue = di;
-        hue = 0.95 + modifier * hue;
-        while hue > 360.0
-            {hue -= 360.0;}
-        while hue < 0.0
-            {hue += 360.0;}
-        return math::hsv_to_rgb(hue as f32, saturation, value) 
+    // calls f with the index and the entry of every pixel, spread over MAX_WORKER threads in bands of rows
+    fn for_each_band<T, F>(pixels: &mut [T], width: i32, height: i32, f: F)
+    where T: Send, F: Fn(usize, &mut T) + Sync, {
+        let rows: usize = (height as usize).div_ceil(MAX_WORKER as usize);
+        thread::scope(|scope| {
+            for (band, chunk) in pixels.chunks_mut(rows * width as usize).enumerate() {
+                let f = &f;
+                scope.spawn(move || {
+                    let first: usize = band * rows * width as usize;
+                    for (j, pixel) in chunk.iter_mut().enumerate() {
+                        f(first + j, pixel);
+                    }
+                });
+            }
+        });
+    }
+
+    // n by n grid of samples inside the pixel, averaged in linear light
+    fn supersample(colorizer: &Colorizer, i: usize, width: i32, height: i32, n: u32) -> [f32; 4] {
+        let mut sum: [f32; 4] = [0.0; 4];
+        for sy in 0..n {
+            for sx in 0..n {
+                let x: f64 = (i as i32 % width) as f64 + (sx as f64 + 0.5) / n as f64 - 0.5;
+                let y: f64 = (i as i32 / width) as f64 + (sy as f64 + 0.5) / n as f64 - 0.5;
+                let color: [f32; 4] = colorizer.color(Mandelbrot::run_mandelbrot(MAX_ITER, Mandelbrot::complex_at(x, y, width, height)).0, i);
+                (0..4).for_each(|c| sum[c] += color[c]);
+            }
+        }
+        let count: f32 = (n * n) as f32;
+        return sum.map(|v| v / count);
+    }
+
+    // the visible part of the complex plane, x and y are pixel coordinates and may be fractional
+    fn complex_at(x: f64, y: f64, width: i32, height: i32) -> Complex<f64> {
+        return Complex::new(-2.0 + (x / width as f64) * (1.0 - -2.0), -1.0 + (y / height as f64) * (1.0 - -1.0));
     }
 
     fn run_mandelbrot(max_iter: i32, num: Complex<f64>) -> (f64, Complex<f64>) {
@@ -154,9 +252,37 @@ impl Mandelbrot {
             z = z*z + num;
             n += 1.0;
         }
+        if n >= max_iter as f64 {
+            return (max_iter as f64, z);
+        }
         return (n + 1.0 - z.abs().ln().ln() / 2.0.ln(), z);
     }
 
+    // 8 bit png comes from the shown image, the other formats from the float frame before dithering
+    pub fn export<P>(&self, filename: P, format: ExportFormat) -> io::Result<()>
+    where P: AsRef<Path>, {
+        let filename: &Path = filename.as_ref();
+        if format == ExportFormat::Png8 {
+            return match self.pixels.save_to_file(&filename.to_string_lossy()) {
+                true => Ok(()),
+                false => Err(io::Error::other("couldn't write the image")),
+            };
+        }
+        let frame: Vec<[f32; 4]> = self.frame();
+        return image_io::save(filename, format, self.pixels.size().x as usize, self.pixels.size().y as usize, &frame);
+    }
+
+    // the refinement average of the last finished frame in linear light with the post effects, before it is quantized
+    pub fn frame(&self) -> Vec<[f32; 4]> {
+        let (frame, settings) = {
+            let accumulation = self.accumulation.lock().unwrap();
+            let samples: f32 = accumulation.samples.max(1) as f32;
+            let frame: Vec<[f32; 4]> = accumulation.sums.iter().map(|sum| sum.map(|v| v / samples)).collect();
+            (frame, accumulation.post)
+        };
+        return post::apply(&frame, self.pixels.size().x as usize, &settings);
+    }
+
     pub fn prepare_for_render(&mut self) {
         match self.tex.load_from_image(&self.pixels, Rect::from_vecs(Vector2i::new(0, 0), Vector2i::new(self.pixels.size().x as i32, self.pixels.size().y as i32))) {
             Ok(_) => (),
@@ -1,59 +1,388 @@
 pub mod math {
-    
+
 
     #[allow(dead_code)]
     pub fn scale(v_max: f32, v_min: f32, o_max: f32, o_min: f32, v: f32) -> f32 {
         return ((v - v_min) / (v_max - v_min)) * (o_max - o_min) + o_min;
     }
+}
 
-    #[allow(dead_code)]
-    pub fn hsv_to_rgb(h: f32, s: f32, v: f32) -> (i32, i32, i32) {
-        if h > 360.0 || h < 0.0 || s > 100.0 || s < 0.0 || v > 100.0 || v < 0.0 {
-            println!("hue {}  saturation {}  value {} ", h, s, v);
-            panic!("error converting hsv to rgb, values outside range");
-        }
-        let s: f32 = s / 100.0;
-        let v: f32 = v / 100.0;
-        let c: f32 = s * v;
-        let x: f32 = c * (1.0 - f32::abs((h / 60.0 % 2.0) - 1.0));
-        let m: f32 = v - c;
-        let r: f32;
-        let g: f32;
-        let b: f32;
-        if h >= 0. && h < 60. {
-            r = c;
-            g = x;
-            b = 0.;
-        }
-        else if h >= 60. && h < 120. {
-            r = x;
-            g = c;
-            b = 0.;
-        }
-        else if h >= 120. && h < 180. {
-            r = 0.;
-            g = c;
-            b = x;
-        }
-        else if h >= 180. && h < 240. {
-            r = 0.;
-            g = x;
-            b = c;
-        }
-        else if h >= 240. && h < 300. {
-            r = x;
-            g = 0.;
-            b = c;
-        }
-        else {
-            r = c;
-            g = 0.;
-            b = x;
-        }
-
-        let r: i32 = ((r + m) * 255.) as i32;
-        let g: i32 = ((g + m) * 255.) as i32;
-        let b: i32 = ((b + m) * 255.) as i32;
-        return (r, g, b);
-    }
-}
\ No newline at end of file
+// typed color values, every component is an f64 so conversions don't lose precision.
+// constructors clamp (and wrap hues) instead of panicking, conversions between types don't clamp
+// so out of gamut colors survive a round trip, clamp when converting to 8 bit
+pub mod color {
+    // gamma encoded srgb, 0 to 1
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Rgb {
+        pub r: f64,
+        pub g: f64,
+        pub b: f64,
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct LinearRgb {
+        pub r: f64,
+        pub g: f64,
+        pub b: f64,
+    }
+
+    // hue in degrees, saturation and value 0 to 1
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Hsv {
+        pub h: f64,
+        pub s: f64,
+        pub v: f64,
+    }
+
+    // hue in degrees, saturation and lightness 0 to 1
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Hsl {
+        pub h: f64,
+        pub s: f64,
+        pub l: f64,
+    }
+
+    // see https://bottosson.github.io/posts/oklab/
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Oklab {
+        pub l: f64,
+        pub a: f64,
+        pub b: f64,
+    }
+
+    // polar oklab, hue in degrees
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub struct Oklch {
+        pub l: f64,
+        pub c: f64,
+        pub h: f64,
+    }
+
+    fn wrap_hue(h: f64) -> f64 {
+        if !h.is_finite() {
+            return 0.0;
+        }
+        return h.rem_euclid(360.0);
+    }
+
+    fn unit(v: f64) -> f64 {
+        if v.is_nan() {
+            return 0.0;
+        }
+        return v.clamp(0.0, 1.0);
+    }
+
+    pub fn srgb_to_linear(c: f64) -> f64 {
+        if c <= 0.04045 {
+            return c / 12.92;
+        }
+        return ((c + 0.055) / 1.055).powf(2.4);
+    }
+
+    pub fn linear_to_srgb(c: f64) -> f64 {
+        if c <= 0.0031308 {
+            return c * 12.92;
+        }
+        return 1.055 * c.powf(1.0 / 2.4) - 0.055;
+    }
+
+    impl Rgb {
+        pub fn new(r: f64, g: f64, b: f64) -> Self {
+            return Rgb { r: unit(r), g: unit(g), b: unit(b) };
+        }
+
+        pub fn from_u8(r: u8, g: u8, b: u8) -> Self {
+            return Rgb { r: r as f64 / 255.0, g: g as f64 / 255.0, b: b as f64 / 255.0 };
+        }
+
+        pub fn to_u8(self) -> (u8, u8, u8) {
+            let c = |v: f64| -> u8 { (unit(v) * 255.0).round() as u8 };
+            return (c(self.r), c(self.g), c(self.b));
+        }
+
+        pub fn clamped(&self) -> Self {
+            return Rgb::new(self.r, self.g, self.b);
+        }
+    }
+
+    impl LinearRgb {
+        pub fn new(r: f64, g: f64, b: f64) -> Self {
+            return LinearRgb { r: unit(r), g: unit(g), b: unit(b) };
+        }
+
+        pub fn lerp(&self, other: LinearRgb, t: f64) -> Self {
+            return LinearRgb { r: self.r + (other.r - self.r) * t, g: self.g + (other.g - self.g) * t, b: self.b + (other.b - self.b) * t };
+        }
+    }
+
+    impl Hsv {
+        pub fn new(h: f64, s: f64, v: f64) -> Self {
+            return Hsv { h: wrap_hue(h), s: unit(s), v: unit(v) };
+        }
+    }
+
+    impl Hsl {
+        // nothing builds one from components yet, colors come from conversions
+        #[allow(dead_code)]
+        pub fn new(h: f64, s: f64, l: f64) -> Self {
+            return Hsl { h: wrap_hue(h), s: unit(s), l: unit(l) };
+        }
+    }
+
+    impl Oklab {
+        // nothing builds one from components yet, colors come from conversions
+        #[allow(dead_code)]
+        pub fn new(l: f64, a: f64, b: f64) -> Self {
+            return Oklab { l: unit(l), a, b };
+        }
+
+        pub fn lerp(&self, other: Oklab, t: f64) -> Self {
+            return Oklab { l: self.l + (other.l - self.l) * t, a: self.a + (other.a - self.a) * t, b: self.b + (other.b - self.b) * t };
+        }
+    }
+
+    impl Oklch {
+        // nothing builds one from components yet, colors come from conversions
+        #[allow(dead_code)]
+        pub fn new(l: f64, c: f64, h: f64) -> Self {
+            return Oklch { l: unit(l), c: c.max(0.0), h: wrap_hue(h) };
+        }
+    }
+
+    // red, green and blue for a hue in degrees with the given chroma and lightness offset
+    fn from_hue(h: f64, c: f64, m: f64) -> Rgb {
+        let h: f64 = wrap_hue(h) / 60.0;
+        let x: f64 = c * (1.0 - (h % 2.0 - 1.0).abs());
+        let (r, g, b) = match h as i32 {
+            0 => (c, x, 0.0),
+            1 => (x, c, 0.0),
+            2 => (0.0, c, x),
+            3 => (0.0, x, c),
+            4 => (x, 0.0, c),
+            _ => (c, 0.0, x),
+        };
+        return Rgb { r: r + m, g: g + m, b: b + m };
+    }
+
+    // hue in degrees, max and chroma of an rgb color
+    fn to_hue(c: &Rgb) -> (f64, f64, f64) {
+        let max: f64 = c.r.max(c.g).max(c.b);
+        let min: f64 = c.r.min(c.g).min(c.b);
+        let chroma: f64 = max - min;
+        let hue: f64 = if chroma == 0.0 {
+            0.0
+        } else if max == c.r {
+            60.0 * ((c.g - c.b) / chroma).rem_euclid(6.0)
+        } else if max == c.g {
+            60.0 * ((c.b - c.r) / chroma + 2.0)
+        } else {
+            60.0 * ((c.r - c.g) / chroma + 4.0)
+        };
+        return (wrap_hue(hue), max, chroma);
+    }
+
+    impl From<Hsv> for Rgb {
+        fn from(c: Hsv) -> Self {
+            let chroma: f64 = c.v * c.s;
+            return from_hue(c.h, chroma, c.v - chroma);
+        }
+    }
+
+    impl From<Rgb> for Hsv {
+        fn from(c: Rgb) -> Self {
+            let (h, max, chroma) = to_hue(&c);
+            let s: f64 = if max == 0.0 { 0.0 } else { chroma / max };
+            return Hsv { h, s, v: max };
+        }
+    }
+
+    impl From<Hsl> for Rgb {
+        fn from(c: Hsl) -> Self {
+            let chroma: f64 = (1.0 - (2.0 * c.l - 1.0).abs()) * c.s;
+            return from_hue(c.h, chroma, c.l - chroma / 2.0);
+        }
+    }
+
+    impl From<Rgb> for Hsl {
+        fn from(c: Rgb) -> Self {
+            let (h, max, chroma) = to_hue(&c);
+            let l: f64 = max - chroma / 2.0;
+            let s: f64 = if l == 0.0 || l == 1.0 { 0.0 } else { chroma / (1.0 - (2.0 * l - 1.0).abs()) };
+            return Hsl { h, s, l };
+        }
+    }
+
+    impl From<Rgb> for LinearRgb {
+        fn from(c: Rgb) -> Self {
+            return LinearRgb { r: srgb_to_linear(c.r), g: srgb_to_linear(c.g), b: srgb_to_linear(c.b) };
+        }
+    }
+
+    impl From<LinearRgb> for Rgb {
+        fn from(c: LinearRgb) -> Self {
+            return Rgb { r: linear_to_srgb(c.r), g: linear_to_srgb(c.g), b: linear_to_srgb(c.b) };
+        }
+    }
+
+    // linear srgb to lms and cube rooted lms to oklab, the inverses are derived from these
+    // instead of using the rounded published ones so a round trip doesn't drift
+    const RGB_TO_LMS: [[f64; 3]; 3] = [
+        [0.4122214708, 0.5363325363, 0.0514459929],
+        [0.2119034982, 0.6806995451, 0.1073969566],
+        [0.0883024619, 0.2817188376, 0.6299787005],
+    ];
+    const LMS_TO_LAB: [[f64; 3]; 3] = [
+        [0.2104542553, 0.7936177850, -0.0040720468],
+        [1.9779984951, -2.4285922050, 0.4505937099],
+        [0.0259040371, 0.7827717662, -0.8086757660],
+    ];
+
+    fn multiply(m: &[[f64; 3]; 3], v: (f64, f64, f64)) -> (f64, f64, f64) {
+        return (
+            m[0][0] * v.0 + m[0][1] * v.1 + m[0][2] * v.2,
+            m[1][0] * v.0 + m[1][1] * v.1 + m[1][2] * v.2,
+            m[2][0] * v.0 + m[2][1] * v.1 + m[2][2] * v.2,
+        );
+    }
+
+    fn invert(m: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+        let det: f64 = m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+            - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+            + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0]);
+        return [
+            [(m[1][1] * m[2][2] - m[1][2] * m[2][1]) / det, (m[0][2] * m[2][1] - m[0][1] * m[2][2]) / det, (m[0][1] * m[1][2] - m[0][2] * m[1][1]) / det],
+            [(m[1][2] * m[2][0] - m[1][0] * m[2][2]) / det, (m[0][0] * m[2][2] - m[0][2] * m[2][0]) / det, (m[0][2] * m[1][0] - m[0][0] * m[1][2]) / det],
+            [(m[1][0] * m[2][1] - m[1][1] * m[2][0]) / det, (m[0][1] * m[2][0] - m[0][0] * m[2][1]) / det, (m[0][0] * m[1][1] - m[0][1] * m[1][0]) / det],
+        ];
+    }
+
+    impl From<LinearRgb> for Oklab {
+        fn from(c: LinearRgb) -> Self {
+            let (l, m, s) = multiply(&RGB_TO_LMS, (c.r, c.g, c.b));
+            let (l, a, b) = multiply(&LMS_TO_LAB, (l.cbrt(), m.cbrt(), s.cbrt()));
+            return Oklab { l, a, b };
+        }
+    }
+
+    impl From<Oklab> for LinearRgb {
+        fn from(c: Oklab) -> Self {
+            let (l, m, s) = multiply(&invert(&LMS_TO_LAB), (c.l, c.a, c.b));
+            let (r, g, b) = multiply(&invert(&RGB_TO_LMS), (l.powi(3), m.powi(3), s.powi(3)));
+            return LinearRgb { r, g, b };
+        }
+    }
+
+    impl From<Rgb> for Oklab {
+        fn from(c: Rgb) -> Self {
+            return Oklab::from(LinearRgb::from(c));
+        }
+    }
+
+    impl From<Oklab> for Rgb {
+        fn from(c: Oklab) -> Self {
+            return Rgb::from(LinearRgb::from(c));
+        }
+    }
+
+    impl From<Oklab> for Oklch {
+        fn from(c: Oklab) -> Self {
+            return Oklch { l: c.l, c: (c.a * c.a + c.b * c.b).sqrt(), h: wrap_hue(c.b.atan2(c.a).to_degrees()) };
+        }
+    }
+
+    impl From<Oklch> for Oklab {
+        fn from(c: Oklch) -> Self {
+            let h: f64 = c.h.to_radians();
+            return Oklab { l: c.l, a: c.c * h.cos(), b: c.c * h.sin() };
+        }
+    }
+
+    impl From<Oklch> for Rgb {
+        fn from(c: Oklch) -> Self {
+            return Rgb::from(Oklab::from(c));
+        }
+    }
+
+    impl From<Rgb> for Oklch {
+        fn from(c: Rgb) -> Self {
+            return Oklch::from(Oklab::from(c));
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        const EPSILON: f64 = 1e-9;
+
+        fn samples() -> Vec<Rgb> {
+            let mut samples: Vec<Rgb> = Vec::new();
+            for r in 0..6 {
+                for g in 0..6 {
+                    for b in 0..6 {
+                        samples.push(Rgb { r: r as f64 / 5.0, g: g as f64 / 5.0, b: b as f64 / 5.0 });
+                    }
+                }
+            }
+            samples.push(Rgb { r: 0.123, g: 0.456, b: 0.789 });
+            return samples;
+        }
+
+        fn assert_close(a: Rgb, b: Rgb) {
+            assert!((a.r - b.r).abs() < EPSILON && (a.g - b.g).abs() < EPSILON && (a.b - b.b).abs() < EPSILON, "{:?} != {:?}", a, b);
+        }
+
+        #[test]
+        fn hsv_round_trip() {
+            samples().into_iter().for_each(|c| assert_close(Rgb::from(Hsv::from(c)), c));
+        }
+
+        #[test]
+        fn hsl_round_trip() {
+            samples().into_iter().for_each(|c| assert_close(Rgb::from(Hsl::from(c)), c));
+        }
+
+        #[test]
+        fn linear_round_trip() {
+            samples().into_iter().for_each(|c| assert_close(Rgb::from(LinearRgb::from(c)), c));
+        }
+
+        #[test]
+        fn oklab_round_trip() {
+            samples().into_iter().for_each(|c| assert_close(Rgb::from(Oklab::from(c)), c));
+        }
+
+        #[test]
+        fn oklch_round_trip() {
+            samples().into_iter().for_each(|c| {
+                let lab: Oklab = Oklab::from(c);
+                let back: Oklab = Oklab::from(Oklch::from(lab));
+                assert!((back.l - lab.l).abs() < EPSILON && (back.a - lab.a).abs() < EPSILON && (back.b - lab.b).abs() < EPSILON);
+            });
+        }
+
+        #[test]
+        fn u8_round_trip() {
+            for v in 0..=255 {
+                assert_eq!(Rgb::from_u8(v, 255 - v, v / 2).to_u8(), (v, 255 - v, v / 2));
+            }
+        }
+
+        #[test]
+        fn known_values() {
+            assert_eq!(Rgb::from(Hsv::new(0.0, 1.0, 1.0)).to_u8(), (255, 0, 0));
+            assert_eq!(Rgb::from(Hsv::new(120.0, 1.0, 1.0)).to_u8(), (0, 255, 0));
+            assert_eq!(Rgb::from(Hsl::new(240.0, 1.0, 0.5)).to_u8(), (0, 0, 255));
+            let white: Oklab = Oklab::from(Rgb::new(1.0, 1.0, 1.0));
+            assert!((white.l - 1.0).abs() < 1e-6 && white.a.abs() < 1e-6 && white.b.abs() < 1e-6);
+        }
+
+        #[test]
+        fn out_of_range_input_is_clamped() {
+            assert_eq!(Hsv::new(370.0, 1.5, -0.5), Hsv { h: 10.0, s: 1.0, v: 0.0 });
+            assert_eq!(Hsl::new(-90.0, 0.5, 2.0), Hsl { h: 270.0, s: 0.5, l: 1.0 });
+            assert_eq!(Rgb::new(f64::NAN, 2.0, -1.0), Rgb { r: 0.0, g: 1.0, b: 0.0 });
+            assert_eq!(Rgb { r: 1.5, g: -0.2, b: 0.5 }.to_u8(), (255, 0, 128));
+        }
+    }
+}
@@ -0,0 +1,104 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{gradient::{Gradient, ColorStop}, math::color::{Rgb, Hsv}};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Harmony {
+    Analogous,
+    Complementary,
+    Triadic,
+}
+
+impl Harmony {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Harmony::Analogous => "analogous",
+            Harmony::Complementary => "complementary",
+            Harmony::Triadic => "triadic",
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        match self {
+            Harmony::Analogous => Harmony::Complementary,
+            Harmony::Complementary => Harmony::Triadic,
+            Harmony::Triadic => Harmony::Analogous,
+        }
+    }
+
+    // hue offsets in degrees from the base hue
+    fn hues(&self) -> &'static [f64] {
+        match self {
+            Harmony::Analogous => &[-30.0, 0.0, 30.0],
+            Harmony::Complementary => &[0.0, 180.0],
+            Harmony::Triadic => &[0.0, 120.0, 240.0],
+        }
+    }
+}
+
+// the same seed and harmony always give the same palette
+pub fn generate(seed: u32, harmony: Harmony) -> Gradient {
+    let mut rng: StdRng = StdRng::seed_from_u64(seed as u64);
+    let base: f64 = rng.gen_range(0.0..360.0);
+    let hues: &[f64] = harmony.hues();
+    let count: usize = rng.gen_range(4..8);
+    let mut stops: Vec<ColorStop> = Vec::with_capacity(count + 1);
+    for i in 0..count {
+        let hue: f64 = (base + hues[i % hues.len()] + rng.gen_range(-10.0..10.0)).rem_euclid(360.0);
+        // alternate dark and light stops so neighboring bands stay distinguishable
+        let value: f64 = if i % 2 == 0 { rng.gen_range(0.15..0.45) } else { rng.gen_range(0.7..1.0) };
+        let saturation: f64 = rng.gen_range(0.4..0.95);
+        let (r, g, b) = Rgb::from(Hsv::new(hue, saturation, value)).to_u8();
+        stops.push(ColorStop::new(i as f64 / count as f64, r, g, b));
+    }
+    // close the loop so the palette repeats without a seam
+    let first: ColorStop = stops[0];
+    stops.push(ColorStop::new(1.0, first.color.0, first.color.1, first.color.2));
+    return Gradient::new(&format!("{}-{}", harmony.name(), seed), stops);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HARMONIES: [Harmony; 3] = [Harmony::Analogous, Harmony::Complementary, Harmony::Triadic];
+
+    type Stops = Vec<(f64, (u8, u8, u8))>;
+
+    fn stops(gradient: &Gradient) -> Stops {
+        return gradient.stops().iter().map(|stop| (stop.position, stop.color)).collect();
+    }
+
+    #[test]
+    fn same_seed_same_palette() {
+        for harmony in HARMONIES.iter() {
+            for seed in [0, 1, 4242, u32::MAX].iter() {
+                assert_eq!(stops(&generate(*seed, *harmony)), stops(&generate(*seed, *harmony)));
+                assert_eq!(generate(*seed, *harmony).name, format!("{}-{}", harmony.name(), seed));
+            }
+        }
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        for harmony in HARMONIES.iter() {
+            let palettes: Vec<Stops> = (0..20).map(|seed| stops(&generate(seed, *harmony))).collect();
+            for (i, a) in palettes.iter().enumerate() {
+                assert!(palettes[i + 1..].iter().all(|b| a != b), "{} {}", harmony.name(), i);
+            }
+        }
+    }
+
+    #[test]
+    fn last_stop_closes_the_loop() {
+        for harmony in HARMONIES.iter() {
+            for seed in 0..20 {
+                let gradient: Gradient = generate(seed, *harmony);
+                let (first, last) = (gradient.stops()[0], gradient.stops()[gradient.stops().len() - 1]);
+                assert_eq!((first.position, last.position), (0.0, 1.0));
+                assert_eq!(first.color, last.color);
+                assert!((5..=9).contains(&gradient.stops().len()));
+            }
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::{collections::HashMap, convert::TryFrom, fmt, fs, path::{Path, PathBuf}};
+
+use yaml_rust::{Yaml, YamlLoader, yaml::{Hash, Array}, parser::{Parser, Event, MarkedEventReceiver}, scanner::Marker};
+
+use crate::{gradient::{Gradient, ColorStop, Interpolation, RepeatMode}, io::io};
+
+// line is 1 based, 0 if the error isn't tied to a line
+#[derive(Debug)]
+pub struct PaletteError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl PaletteError {
+    fn new(line: usize, message: &str) -> Self {
+        return PaletteError { line, message: message.to_string() };
+    }
+}
+
+impl fmt::Display for PaletteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        return write!(f, "line {}: {}", self.line, self.message);
+    }
+}
+
+// picks the parser from the file extension, a file can hold more than one gradient (.ugr)
+pub fn load<P>(filename: P) -> Result<Vec<Gradient>, PaletteError>
+where P: AsRef<Path>, {
+    let path: &Path = filename.as_ref();
+    let name: String = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let reader = match io::read_lines(path) {
+        Ok(x) => x,
+        Err(e) => return Err(PaletteError::new(0, &e.to_string())),
+    };
+    // a line that can't be read (or isn't utf-8) fails the whole file
+    let mut lines: Vec<String> = Vec::new();
+    for (i, line) in reader.enumerate() {
+        match line {
+            Ok(x) => lines.push(x),
+            Err(e) => return Err(PaletteError::new(i + 1, &e.to_string())),
+        }
+    }
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("map") => parse_map(&name, &lines).map(|gradient| vec![gradient]),
+        Some("ggr") => parse_ggr(&name, &lines).map(|gradient| vec![gradient]),
+        Some("ugr") => parse_ugr(&lines),
+        Some("yaml") | Some("yml") => parse_yaml(&lines.join("\n")).map(|gradient| vec![gradient]),
+        _ => Err(PaletteError::new(0, "unknown palette format")),
+    }
+}
+
+// an exported palette is saved as yaml and as map, the map only has 256 colors for other programs.
+// true for a map with a yaml of the same name next to it, it isn't loaded so the palette is listed once
+pub fn is_shadowed(path: &Path) -> bool {
+    if path.extension().and_then(|ext| ext.to_str()) != Some("map") {
+        return false;
+    }
+    return path.with_extension("yaml").exists() || path.with_extension("yml").exists();
+}
+
+// loads every palette file in a directory together with the file it came from,
+// files that fail to parse are returned as messages
+pub fn load_directory<P>(directory: P) -> (Vec<(PathBuf, Gradient)>, Vec<String>)
+where P: AsRef<Path>, {
+    let mut gradients: Vec<(PathBuf, Gradient)> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    let mut paths: Vec<_> = match fs::read_dir(directory) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect(),
+        Err(_) => return (gradients, errors),
+    };
+    paths.sort();
+    for path in paths.into_iter().filter(|path| !is_shadowed(path)) {
+        match load(&path) {
+            Ok(loaded) => gradients.extend(loaded.into_iter().map(|gradient| (path.clone(), gradient))),
+            Err(e) => errors.push(format!("{}: {}", path.display(), e)),
+        }
+    }
+    return (gradients, errors);
+}
+
+// fractint map: one "r g b" entry per line, anything after the third number is a comment
+pub fn parse_map(name: &str, lines: &[String]) -> Result<Gradient, PaletteError> {
+    let mut colors: Vec<(u8, u8, u8)> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let fields: Vec<&str> = line.split_whitespace().take(3).collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields.len() < 3 {
+            return Err(PaletteError::new(i + 1, "expected three color components"));
+        }
+        let mut rgb: [u8; 3] = [0; 3];
+        for (c, field) in fields.iter().enumerate() {
+            rgb[c] = match field.parse::<u8>() {
+                Ok(x) => x,
+                Err(_) => return Err(PaletteError::new(i + 1, &format!("\"{}\" is not a color component from 0 to 255", field))),
+            };
+        }
+        colors.push((rgb[0], rgb[1], rgb[2]));
+    }
+    if colors.is_empty() {
+        return Err(PaletteError::new(0, "map file has no colors"));
+    }
+    let last: f64 = (colors.len() - 1).max(1) as f64;
+    let stops: Vec<ColorStop> = colors.iter().enumerate().map(|(i, c)| ColorStop::new(i as f64 / last, c.0, c.1, c.2)).collect();
+    return Ok(Gradient::new(name, stops));
+}
+
+// gimp gradient: a header, an optional name, the segment count and one segment per line
+// "left middle right r0 g0 b0 a0 r1 g1 b1 a1 type color", the middle point and blending type are approximated as linear
+pub fn parse_ggr(name: &str, lines: &[String]) -> Result<Gradient, PaletteError> {
+    let mut lines = lines.iter().enumerate().filter(|(_, line)| !line.trim().is_empty());
+    match lines.next() {
+        Some((_, line)) if line.trim() == "GIMP Gradient" => (),
+        Some((i, _)) => return Err(PaletteError::new(i + 1, "missing \"GIMP Gradient\" header")),
+        None => return Err(PaletteError::new(0, "empty gradient file")),
+    };
+    let mut name: String = name.to_string();
+    let mut segments: Option<usize> = None;
+    let mut stops: Vec<ColorStop> = Vec::new();
+    for (i, line) in lines {
+        let line: &str = line.trim();
+        if let Some(n) = line.strip_prefix("Name:") {
+            name = n.trim().to_string();
+            continue;
+        }
+        if segments.is_none() {
+            segments = match line.parse::<usize>() {
+                Ok(x) => Some(x),
+                Err(_) => return Err(PaletteError::new(i + 1, "expected the number of segments")),
+            };
+            continue;
+        }
+        let values: Vec<f64> = match line.split_whitespace().map(|v| v.parse::<f64>()).collect() {
+            Ok(x) => x,
+            Err(_) => return Err(PaletteError::new(i + 1, "segment values must be numbers")),
+        };
+        if values.len() < 11 {
+            return Err(PaletteError::new(i + 1, "a segment needs at least 11 values"));
+        }
+        let channel = |v: f64| -> u8 { (v.clamp(0.0, 1.0) * 255.0).round() as u8 };
+        stops.push(ColorStop::new(values[0], channel(values[3]), channel(values[4]), channel(values[5])));
+        stops.push(ColorStop::new(values[2], channel(values[7]), channel(values[8]), channel(values[9])));
+    }
+    if stops.is_empty() || Some(stops.len() / 2) != segments {
+        return Err(PaletteError::new(0, "segment count doesn't match the number of segments"));
+    }
+    return Ok(Gradient::new(&name, stops));
+}
+
+// ultra fractal gradient: "name { ... }" blocks with "index=n color=bgr" entries in the gradient: section,
+// index goes from 0 to 399 and other sections (opacity:) are skipped
+pub fn parse_ugr(lines: &[String]) -> Result<Vec<Gradient>, PaletteError> {
+    let mut gradients: Vec<Gradient> = Vec::new();
+    let mut name: Option<String> = None;
+    let mut section: String = String::new();
+    let mut stops: Vec<ColorStop> = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let line: &str = line.trim();
+        if let Some(header) = line.strip_suffix('{') {
+            name = Some(header.trim().to_string());
+            section.clear();
+            stops.clear();
+            continue;
+        }
+        if line.ends_with(':') && !line.contains('=') {
+            section = line.to_string();
+            continue;
+        }
+        if line == "}" {
+            match name.take() {
+                Some(n) if !stops.is_empty() => gradients.push(Gradient::new(&n, stops.clone())),
+                Some(_) => return Err(PaletteError::new(i + 1, "gradient has no colors")),
+                None => return Err(PaletteError::new(i + 1, "unexpected \"}\"")),
+            }
+            continue;
+        }
+        if section != "gradient:" {
+            continue;
+        }
+        let mut index: Option<f64> = None;
+        let mut color: Option<u32> = None;
+        for field in line.split_whitespace() {
+            if let Some(v) = field.strip_prefix("index=") {
+                index = v.parse::<f64>().ok();
+                if index.is_none() {
+                    return Err(PaletteError::new(i + 1, "index must be a number"));
+                }
+            }
+            if let Some(v) = field.strip_prefix("color=") {
+                color = v.parse::<u32>().ok();
+                if color.is_none() {
+                    return Err(PaletteError::new(i + 1, "color must be a number"));
+                }
+            }
+        }
+        match (index, color) {
+            (Some(index), Some(color)) => {
+                if name.is_none() {
+                    return Err(PaletteError::new(i + 1, "color entry outside of a gradient block"));
+                }
+                stops.push(ColorStop::new(index.rem_euclid(400.0) / 400.0, (color & 0xff) as u8, (color >> 8 & 0xff) as u8, (color >> 16 & 0xff) as u8));
+            }
+            (Some(_), None) | (None, Some(_)) => return Err(PaletteError::new(i + 1, "expected both index= and color=")),
+            (None, None) => (),
+        }
+    }
+    if name.is_some() {
+        return Err(PaletteError::new(lines.len(), "missing \"}\" at the end of the file"));
+    }
+    if gradients.is_empty() {
+        return Err(PaletteError::new(0, "no gradients in file"));
+    }
+    return Ok(gradients);
+}
+
+// the Yaml tree has no positions, so the lines of the top level values and of every stop are
+// collected from the parser events of the first document
+#[derive(Default)]
+struct YamlLines {
+    depth: usize,
+    key: Option<String>,
+    expect_key: bool,
+    done: bool,
+    values: HashMap<String, usize>,
+    stops: Vec<usize>,
+}
+
+impl YamlLines {
+    fn new(contents: &str) -> Self {
+        let mut lines: YamlLines = YamlLines { expect_key: true, ..YamlLines::default() };
+        // only called once YamlLoader has read the contents, so there is no error left to report
+        let _ = Parser::new(contents.chars()).load(&mut lines, false);
+        return lines;
+    }
+
+    fn value(&self, key: &str) -> usize {
+        return self.values.get(key).copied().unwrap_or(0);
+    }
+}
+
+impl MarkedEventReceiver for YamlLines {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.done {
+            return;
+        }
+        let node: bool = matches!(event, Event::Scalar(..) | Event::SequenceStart(..) | Event::MappingStart(..) | Event::Alias(..));
+        if node && self.depth == 1 {
+            if self.expect_key {
+                self.key = match &event {
+                    Event::Scalar(key, ..) => Some(key.clone()),
+                    _ => None,
+                };
+            } else if let Some(key) = &self.key {
+                self.values.insert(key.clone(), mark.line());
+            }
+            self.expect_key = !self.expect_key;
+        }
+        if node && self.depth == 2 && self.key.as_deref() == Some("stops") {
+            self.stops.push(mark.line());
+        }
+        match event {
+            Event::SequenceStart(..) | Event::MappingStart(..) => self.depth += 1,
+            Event::SequenceEnd | Event::MappingEnd => self.depth -= 1,
+            Event::DocumentEnd => self.done = true,
+            _ => (),
+        }
+    }
+}
+
+pub fn parse_yaml(contents: &str) -> Result<Gradient, PaletteError> {
+    let docs: Vec<Yaml> = match YamlLoader::load_from_str(contents) {
+        Ok(x) => x,
+        Err(e) => return Err(PaletteError::new(e.marker().line(), &e.to_string())),
+    };
+    let lines: YamlLines = YamlLines::new(contents);
+    let yaml: &Yaml = match docs.first() {
+        Some(x) => x,
+        None => return Err(PaletteError::new(0, "empty palette file")),
+    };
+    let name: &str = yaml["name"].as_str().unwrap_or("unnamed");
+    let entries: &Array = match yaml["stops"].as_vec() {
+        Some(x) => x,
+        None => return Err(PaletteError::new(lines.value("stops"), "palette needs a list of stops")),
+    };
+    let mut stops: Vec<ColorStop> = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let position: Option<f64> = entry["position"].as_f64().or(entry["position"].as_i64().map(|v| v as f64));
+        let color: Option<Vec<u8>> = entry["color"].as_vec().and_then(|c| c.iter().map(|v| v.as_i64().and_then(|v| u8::try_from(v).ok())).collect());
+        match (position, color) {
+            (Some(position), Some(color)) if color.len() == 3 => stops.push(ColorStop::new(position, color[0], color[1], color[2])),
+            _ => return Err(PaletteError::new(lines.stops.get(i).copied().unwrap_or(0), &format!("stop {} needs a position and a color [r, g, b]", i + 1))),
+        }
+    }
+    let mut gradient: Gradient = Gradient::new(name, stops);
+    if let Some(interpolation) = yaml["interpolation"].as_str() {
+        gradient.interpolation = match Interpolation::from_name(interpolation) {
+            Some(x) => x,
+            None => return Err(PaletteError::new(lines.value("interpolation"), &format!("unknown interpolation \"{}\"", interpolation))),
+        };
+    }
+    if let Some(repeat) = yaml["repeat"].as_str() {
+        gradient.repeat = match RepeatMode::from_name(repeat) {
+            Some(x) => x,
+            None => return Err(PaletteError::new(lines.value("repeat"), &format!("unknown repeat mode \"{}\"", repeat))),
+        };
+    }
+    return Ok(gradient);
+}
+
+// a file name without extension for a palette name, imported names can hold path separators or "..".
+// dots are replaced so an extension can be added, None if nothing usable is left
+pub fn file_name(name: &str) -> Option<String> {
+    let cleaned: String = name.chars().map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' { c } else { '_' }).collect();
+    let cleaned: &str = cleaned.trim().trim_matches('_');
+    if cleaned.is_empty() {
+        return None;
+    }
+    return Some(cleaned.to_string());
+}
+
+// 256 entries like fractint expects
+pub fn save_map<P>(filename: P, gradient: &Gradient) -> std::io::Result<()>
+where P: AsRef<Path>, {
+    let mut contents: String = String::new();
+    for (r, g, b) in gradient.lookup_table(256) {
+        contents.push_str(&format!("{} {} {}\n", r, g, b));
+    }
+    return fs::write(filename, contents);
+}
+
+pub fn save_yaml<P>(filename: P, gradient: &Gradient) -> std::io::Result<()>
+where P: AsRef<Path>, {
+    let mut hash: Hash = Hash::new();
+    hash.insert(Yaml::String("name".to_string()), Yaml::String(gradient.name.clone()));
+    hash.insert(Yaml::String("interpolation".to_string()), Yaml::String(gradient.interpolation.name().to_string()));
+    hash.insert(Yaml::String("repeat".to_string()), Yaml::String(gradient.repeat.name().to_string()));
+    let stops: Array = gradient.stops().iter().map(|stop| {
+        let mut entry: Hash = Hash::new();
+        entry.insert(Yaml::String("position".to_string()), Yaml::Real(stop.position.to_string()));
+        entry.insert(Yaml::String("color".to_string()), Yaml::Array(vec![Yaml::Integer(stop.color.0.into()), Yaml::Integer(stop.color.1.into()), Yaml::Integer(stop.color.2.into())]));
+        Yaml::Hash(entry)
+    }).collect();
+    hash.insert(Yaml::String("stops".to_string()), Yaml::Array(stops));
+    return io::write_yaml(filename, &Yaml::Hash(hash));
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        return text.lines().map(|line| line.to_string()).collect();
+    }
+
+    fn colors(gradient: &Gradient) -> Vec<(f64, (u8, u8, u8))> {
+        return gradient.stops().iter().map(|stop| (stop.position, stop.color)).collect();
+    }
+
+    // the line of the error, panics on success
+    fn error_line<T>(result: Result<T, PaletteError>) -> usize {
+        match result {
+            Ok(_) => panic!("expected an error"),
+            Err(e) => e.line,
+        }
+    }
+
+    // a file in the temp directory that is removed again when the test is done
+    struct TempFile(PathBuf);
+
+    impl TempFile {
+        fn new(name: &str, contents: &[u8]) -> Self {
+            let path: PathBuf = env::temp_dir().join(format!("mandelbrot-test-{}-{}", std::process::id(), name));
+            fs::write(&path, contents).unwrap();
+            return TempFile(path);
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    #[test]
+    fn map() {
+        let gradient: Gradient = parse_map("fire", &lines("0 0 0 black\n\n255 128 0\n255 255 255 the end")).unwrap();
+        assert_eq!(gradient.name, "fire");
+        assert_eq!(colors(&gradient), vec![(0.0, (0, 0, 0)), (0.5, (255, 128, 0)), (1.0, (255, 255, 255))]);
+    }
+
+    #[test]
+    fn map_errors() {
+        assert_eq!(error_line(parse_map("m", &lines("0 0 0\n1 2"))), 2);
+        assert_eq!(error_line(parse_map("m", &lines("0 0 0\n\n1 2 256"))), 3);
+        assert_eq!(error_line(parse_map("m", &lines("0 0 0\n1 -2 3"))), 2);
+        assert_eq!(error_line(parse_map("m", &lines("\n  \n"))), 0);
+    }
+
+    #[test]
+    fn ggr() {
+        let text: &str = "GIMP Gradient\nName: Sunrise\n2\n0 0.25 0.5 1 0 0 1 0 1 0 1 0 0\n0.5 0.75 1 0 1 0 1 0 0 1 1 0 0\n";
+        let gradient: Gradient = parse_ggr("file", &lines(text)).unwrap();
+        assert_eq!(gradient.name, "Sunrise");
+        assert_eq!(colors(&gradient), vec![(0.0, (255, 0, 0)), (0.5, (0, 255, 0)), (0.5, (0, 255, 0)), (1.0, (0, 0, 255))]);
+        // without a name the file name is kept
+        assert_eq!(parse_ggr("file", &lines("GIMP Gradient\n1\n0 0.5 1 0 0 0 1 1 1 1 1 0 0")).unwrap().name, "file");
+    }
+
+    #[test]
+    fn ggr_errors() {
+        assert_eq!(error_line(parse_ggr("g", &lines("\nGIMP Palette\n1"))), 2);
+        assert_eq!(error_line(parse_ggr("g", &lines(""))), 0);
+        assert_eq!(error_line(parse_ggr("g", &lines("GIMP Gradient\nName: g\ntwo"))), 3);
+        assert_eq!(error_line(parse_ggr("g", &lines("GIMP Gradient\n1\n0 0.5 1 0 0 0 1 1 x 1 1 0 0"))), 3);
+        assert_eq!(error_line(parse_ggr("g", &lines("GIMP Gradient\n1\n0 0.5 1 0 0 0 1 1 1"))), 3);
+        assert_eq!(error_line(parse_ggr("g", &lines("GIMP Gradient\n2\n0 0.5 1 0 0 0 1 1 1 1 1 0 0"))), 0);
+    }
+
+    #[test]
+    fn ugr() {
+        let text: &str = "first {\ngradient:\n  title=\"first\" smooth=no\n  index=0 color=255\n  index=200 color=65280\nopacity:\n  index=0 color=7\n}\nsecond {\ngradient:\n  index=100 color=16711680\n}\n";
+        let gradients: Vec<Gradient> = parse_ugr(&lines(text)).unwrap();
+        assert_eq!(gradients.len(), 2);
+        assert_eq!(gradients[0].name, "first");
+        // colors are stored as bgr
+        assert_eq!(colors(&gradients[0]), vec![(0.0, (255, 0, 0)), (0.5, (0, 255, 0))]);
+        assert_eq!(colors(&gradients[1]), vec![(0.25, (0, 0, 255))]);
+    }
+
+    #[test]
+    fn ugr_errors() {
+        assert_eq!(error_line(parse_ugr(&lines("a {\ngradient:\n}"))), 3);
+        assert_eq!(error_line(parse_ugr(&lines("}"))), 1);
+        assert_eq!(error_line(parse_ugr(&lines("a {\ngradient:\nindex=x color=0\n}"))), 3);
+        assert_eq!(error_line(parse_ugr(&lines("a {\ngradient:\nindex=0 color=red\n}"))), 3);
+        assert_eq!(error_line(parse_ugr(&lines("gradient:\nindex=0 color=0"))), 2);
+        assert_eq!(error_line(parse_ugr(&lines("a {\ngradient:\nindex=0\n}"))), 3);
+        assert_eq!(error_line(parse_ugr(&lines("a {\ngradient:\nindex=0 color=0"))), 3);
+        assert_eq!(error_line(parse_ugr(&lines("comment\n"))), 0);
+    }
+
+    #[test]
+    fn yaml() {
+        let text: &str = "name: ocean\ninterpolation: linear rgb\nrepeat: mirror\nstops:\n  - position: 0\n    color: [0, 0, 64]\n  - position: 1.0\n    color: [200, 255, 255]\n";
+        let gradient: Gradient = parse_yaml(text).unwrap();
+        assert_eq!(gradient.name, "ocean");
+        assert!(gradient.interpolation == Interpolation::LinearRgb && gradient.repeat == RepeatMode::Mirror);
+        assert_eq!(colors(&gradient), vec![(0.0, (0, 0, 64)), (1.0, (200, 255, 255))]);
+    }
+
+    #[test]
+    fn yaml_errors() {
+        // syntax errors, the line is the one of the parser
+        assert_eq!(error_line(parse_yaml("name: a\nstops: [\n")), 3);
+        assert_eq!(error_line(parse_yaml("")), 0);
+        assert_eq!(error_line(parse_yaml("name: a\n\nstops: 3\n")), 3);
+        assert_eq!(error_line(parse_yaml("name: a\n")), 0);
+        let stops: &str = "stops:\n  - position: 0\n    color: [0, 0, 0]\n  - position: 1\n    color: [0, 300, 0]\n";
+        assert_eq!(error_line(parse_yaml(stops)), 4);
+        assert_eq!(error_line(parse_yaml("stops:\n  - {position: 0, color: [1, 2]}\n")), 2);
+        assert_eq!(error_line(parse_yaml("stops:\n  - color: [1, 2, 3]\n")), 2);
+        assert_eq!(error_line(parse_yaml("stops: []\ninterpolation: cubic\n")), 2);
+        assert_eq!(error_line(parse_yaml("repeat: clamp\n\nstops: []\n")), 1);
+    }
+
+    #[test]
+    fn yaml_round_trip() {
+        let mut gradient: Gradient = Gradient::new("round trip", vec![ColorStop::new(0.0, 1, 2, 3), ColorStop::new(0.125, 250, 128, 0), ColorStop::new(1.0, 255, 255, 255)]);
+        gradient.interpolation = Interpolation::LinearRgb;
+        gradient.repeat = RepeatMode::Mirror;
+        let file: TempFile = TempFile::new("round-trip.yaml", b"");
+        save_yaml(&file.0, &gradient).unwrap();
+        let loaded: Vec<Gradient> = load(&file.0).unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].name, gradient.name);
+        assert!(loaded[0].interpolation == gradient.interpolation && loaded[0].repeat == gradient.repeat);
+        assert_eq!(colors(&loaded[0]), colors(&gradient));
+    }
+
+    #[test]
+    fn file_names() {
+        assert_eq!(file_name("analogous-1234"), Some("analogous-1234".to_string()));
+        assert_eq!(file_name(" Sunset glow "), Some("Sunset glow".to_string()));
+        assert_eq!(file_name("../../etc/passwd"), Some("etc_passwd".to_string()));
+        assert_eq!(file_name("a/b\\c:d"), Some("a_b_c_d".to_string()));
+        assert_eq!(file_name("v1.2"), Some("v1_2".to_string()));
+        assert_eq!(file_name("Ünïcode"), Some("Ünïcode".to_string()));
+        assert_eq!(file_name(".."), None);
+        assert_eq!(file_name("  /  "), None);
+        assert_eq!(file_name(""), None);
+    }
+
+    #[test]
+    fn unreadable_lines_are_errors() {
+        let file: TempFile = TempFile::new("invalid.map", b"0 0 0\n255 \xff 0\n");
+        assert_eq!(error_line(load(&file.0)), 2);
+        assert_eq!(error_line(load(env::temp_dir().join("mandelbrot-test-missing.map"))), 0);
+    }
+
+    #[test]
+    fn exported_maps_are_not_loaded_twice() {
+        let directory: PathBuf = env::temp_dir().join(format!("mandelbrot-test-{}-palettes", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let exported: Gradient = parse_map("sunset", &lines("255 0 0\n0 0 255")).unwrap();
+        save_yaml(directory.join("sunset.yaml"), &exported).unwrap();
+        save_map(directory.join("sunset.map"), &exported).unwrap();
+        fs::write(directory.join("other.map"), "0 0 0\n255 255 255\n").unwrap();
+        let (loaded, errors) = load_directory(&directory);
+        let files: Vec<String> = loaded.iter().map(|(path, _)| path.file_name().unwrap().to_string_lossy().to_string()).collect();
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(errors.is_empty());
+        assert_eq!(files, vec!["other.map", "sunset.yaml"]);
+        assert!(!is_shadowed(&directory.join("other.yaml")));
+    }
+}
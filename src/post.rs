@@ -0,0 +1,236 @@
+use std::thread;
+
+use crate::math::color;
+
+const WORKERS: usize = 8;
+
+// radii are gaussian sigmas in pixels, colors are in linear light like the frame
+#[derive(Clone, Copy, PartialEq)]
+pub struct Blur {
+    pub radius: f64,
+}
+
+// glow from everything brighter than threshold
+#[derive(Clone, Copy, PartialEq)]
+pub struct Bloom {
+    pub threshold: f64,
+    pub radius: f64,
+    pub strength: f64,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Unsharp {
+    pub radius: f64,
+    pub amount: f64,
+}
+
+// darkens towards the corners, radius is where it starts as a fraction of the center to corner distance
+#[derive(Clone, Copy, PartialEq)]
+pub struct Vignette {
+    pub strength: f64,
+    pub radius: f64,
+}
+
+// black and white point from 0 to 1 and a gamma on the gamma encoded values, like the levels dialog of an editor
+#[derive(Clone, Copy, PartialEq)]
+pub struct Levels {
+    pub black: f64,
+    pub white: f64,
+    pub gamma: f64,
+}
+
+// the effects always run in the order of the fields, None skips one
+#[derive(Clone, Copy, PartialEq)]
+pub struct PostSettings {
+    pub blur: Option<Blur>,
+    pub bloom: Option<Bloom>,
+    pub unsharp: Option<Unsharp>,
+    pub vignette: Option<Vignette>,
+    pub levels: Option<Levels>,
+}
+
+impl PostSettings {
+    pub fn none() -> Self {
+        return PostSettings { blur: None, bloom: None, unsharp: None, vignette: None, levels: None };
+    }
+}
+
+// frame is row major, returns the processed copy
+pub fn apply(frame: &[[f32; 4]], width: usize, settings: &PostSettings) -> Vec<[f32; 4]> {
+    let mut frame: Vec<[f32; 4]> = frame.to_vec();
+    if width == 0 {
+        return frame;
+    }
+    if let Some(blur) = settings.blur {
+        frame = gaussian_blur(&frame, width, blur.radius);
+    }
+    if let Some(bloom) = settings.bloom {
+        let threshold: f32 = bloom.threshold as f32;
+        let bright: Vec<[f32; 4]> = frame.iter().map(|p| [(p[0] - threshold).max(0.0), (p[1] - threshold).max(0.0), (p[2] - threshold).max(0.0), 0.0]).collect();
+        let glow: Vec<[f32; 4]> = gaussian_blur(&bright, width, bloom.radius);
+        for (p, g) in frame.iter_mut().zip(glow.iter()) {
+            (0..3).for_each(|c| p[c] += g[c] * bloom.strength as f32);
+        }
+    }
+    if let Some(unsharp) = settings.unsharp {
+        let blurred: Vec<[f32; 4]> = gaussian_blur(&frame, width, unsharp.radius);
+        for (p, b) in frame.iter_mut().zip(blurred.iter()) {
+            (0..3).for_each(|c| p[c] = (p[c] + (p[c] - b[c]) * unsharp.amount as f32).max(0.0));
+        }
+    }
+    if let Some(vignette) = settings.vignette {
+        let height: usize = frame.len() / width;
+        let (cx, cy) = (width as f64 / 2.0, height as f64 / 2.0);
+        let corner: f64 = (cx * cx + cy * cy).sqrt();
+        for (i, p) in frame.iter_mut().enumerate() {
+            let (dx, dy) = ((i % width) as f64 + 0.5 - cx, (i / width) as f64 + 0.5 - cy);
+            let d: f64 = ((dx * dx + dy * dy).sqrt() / corner - vignette.radius) / (1.0 - vignette.radius).max(1e-6);
+            // smoothstep from the radius out to the corners
+            let d: f64 = d.clamp(0.0, 1.0);
+            let falloff: f32 = (1.0 - vignette.strength * d * d * (3.0 - 2.0 * d)) as f32;
+            (0..3).for_each(|c| p[c] *= falloff);
+        }
+    }
+    if let Some(levels) = settings.levels {
+        let range: f64 = (levels.white - levels.black).max(1e-6);
+        let gamma: f64 = levels.gamma.max(1e-6);
+        let adjust = |v: f32| -> f32 {
+            let encoded: f64 = color::linear_to_srgb(v.clamp(0.0, 1.0) as f64);
+            let leveled: f64 = ((encoded - levels.black) / range).clamp(0.0, 1.0).powf(1.0 / gamma);
+            color::srgb_to_linear(leveled) as f32
+        };
+        for p in frame.iter_mut() {
+            (0..3).for_each(|c| p[c] = adjust(p[c]));
+        }
+    }
+    return frame;
+}
+
+// separable, edges repeat the border pixels
+fn gaussian_blur(frame: &[[f32; 4]], width: usize, sigma: f64) -> Vec<[f32; 4]> {
+    if sigma <= 0.0 {
+        return frame.to_vec();
+    }
+    let radius: isize = (sigma * 3.0).ceil() as isize;
+    let mut kernel: Vec<f32> = (-radius..=radius).map(|k| (-(k * k) as f64 / (2.0 * sigma * sigma)).exp() as f32).collect();
+    let total: f32 = kernel.iter().sum();
+    kernel.iter_mut().for_each(|w| *w /= total);
+    let height: usize = frame.len() / width;
+
+    let mut horizontal: Vec<[f32; 4]> = vec![[0.0; 4]; frame.len()];
+    for_each_row(&mut horizontal, width, |y, row| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let mut sum: [f32; 4] = [0.0; 4];
+            for k in -radius..=radius {
+                let sx: usize = (x as isize + k).clamp(0, width as isize - 1) as usize;
+                let p: [f32; 4] = frame[y * width + sx];
+                let w: f32 = kernel[(k + radius) as usize];
+                (0..4).for_each(|c| sum[c] += p[c] * w);
+            }
+            *out = sum;
+        }
+    });
+    let mut blurred: Vec<[f32; 4]> = vec![[0.0; 4]; frame.len()];
+    for_each_row(&mut blurred, width, |y, row| {
+        for (x, out) in row.iter_mut().enumerate() {
+            let mut sum: [f32; 4] = [0.0; 4];
+            for k in -radius..=radius {
+                let sy: usize = (y as isize + k).clamp(0, height as isize - 1) as usize;
+                let p: [f32; 4] = horizontal[sy * width + x];
+                let w: f32 = kernel[(k + radius) as usize];
+                (0..4).for_each(|c| sum[c] += p[c] * w);
+            }
+            *out = sum;
+        }
+    });
+    return blurred;
+}
+
+// calls f with the row number and the pixels of every row, spread over WORKERS threads in bands
+fn for_each_row<F>(frame: &mut [[f32; 4]], width: usize, f: F)
+where F: Fn(usize, &mut [[f32; 4]]) + Sync, {
+    let rows: usize = (frame.len() / width).div_ceil(WORKERS).max(1);
+    thread::scope(|scope| {
+        for (band, chunk) in frame.chunks_mut(rows * width).enumerate() {
+            let f = &f;
+            scope.spawn(move || {
+                for (j, row) in chunk.chunks_mut(width).enumerate() {
+                    f(band * rows + j, row);
+                }
+            });
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 16;
+    const HEIGHT: usize = 12;
+
+    fn constant(v: f32) -> Vec<[f32; 4]> {
+        return vec![[v, v, v, 1.0]; WIDTH * HEIGHT];
+    }
+
+    fn gradient() -> Vec<[f32; 4]> {
+        return (0..WIDTH * HEIGHT).map(|i| {
+            let (x, y) = ((i % WIDTH) as f32 / WIDTH as f32, (i / WIDTH) as f32 / HEIGHT as f32);
+            [x, y, (x * 7.0 + y * 3.0).fract(), 1.0]
+        }).collect();
+    }
+
+    fn assert_close(a: &[[f32; 4]], b: &[[f32; 4]], epsilon: f32) {
+        for (p, q) in a.iter().zip(b.iter()) {
+            assert!((0..4).all(|c| (p[c] - q[c]).abs() < epsilon), "{:?} != {:?}", p, q);
+        }
+    }
+
+    #[test]
+    fn zero_strength_is_identity() {
+        let frame: Vec<[f32; 4]> = gradient();
+        let settings: PostSettings = PostSettings {
+            blur: Some(Blur { radius: 0.0 }),
+            bloom: Some(Bloom { threshold: 0.2, radius: 3.0, strength: 0.0 }),
+            unsharp: Some(Unsharp { radius: 2.0, amount: 0.0 }),
+            vignette: Some(Vignette { strength: 0.0, radius: 0.3 }),
+            levels: Some(Levels { black: 0.0, white: 1.0, gamma: 1.0 }),
+        };
+        assert_close(&apply(&frame, WIDTH, &settings), &frame, 1e-5);
+        assert_eq!(apply(&frame, WIDTH, &PostSettings::none()), frame);
+    }
+
+    #[test]
+    fn blur_keeps_a_constant_frame() {
+        let settings: PostSettings = PostSettings { blur: Some(Blur { radius: 2.5 }), ..PostSettings::none() };
+        assert_close(&apply(&constant(0.4), WIDTH, &settings), &constant(0.4), 1e-5);
+    }
+
+    #[test]
+    fn levels_on_a_constant_frame() {
+        // half way up in srgb is moved to white by a white point of one half
+        let mid: f32 = color::srgb_to_linear(0.5) as f32;
+        let white: PostSettings = PostSettings { levels: Some(Levels { black: 0.0, white: 0.5, gamma: 1.0 }), ..PostSettings::none() };
+        assert_close(&apply(&constant(mid), WIDTH, &white), &constant(1.0), 1e-5);
+        let black: PostSettings = PostSettings { levels: Some(Levels { black: 0.5, white: 1.0, gamma: 1.0 }), ..PostSettings::none() };
+        assert_close(&apply(&constant(mid), WIDTH, &black), &constant(0.0), 1e-5);
+        // a gamma above one brightens the midtones
+        let quarter: f32 = color::srgb_to_linear(0.25) as f32;
+        let gamma: PostSettings = PostSettings { levels: Some(Levels { black: 0.0, white: 1.0, gamma: 2.0 }), ..PostSettings::none() };
+        assert_close(&apply(&constant(quarter), WIDTH, &gamma), &constant(mid), 1e-5);
+    }
+
+    #[test]
+    fn vignette_on_a_constant_frame() {
+        let settings: PostSettings = PostSettings { vignette: Some(Vignette { strength: 0.8, radius: 0.5 }), ..PostSettings::none() };
+        let out: Vec<[f32; 4]> = apply(&constant(1.0), WIDTH, &settings);
+        // the center is untouched and the corners get darker than their neighbours closer in
+        let center: usize = HEIGHT / 2 * WIDTH + WIDTH / 2;
+        assert_eq!(out[center], [1.0, 1.0, 1.0, 1.0]);
+        assert!(out[0][0] < out[WIDTH + 1][0] && out[WIDTH + 1][0] < out[center][0]);
+        assert!(out[0][0] > 0.2 && out[0][3] == 1.0);
+        // and it is symmetric
+        assert!((out[0][0] - out[WIDTH * HEIGHT - 1][0]).abs() < 1e-6);
+        assert!((out[WIDTH - 1][0] - out[WIDTH * (HEIGHT - 1)][0]).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,70 @@
+use std::{fs, path::{Path, PathBuf}, time::{Duration, Instant, SystemTime}};
+
+// notices changed files by polling their modification times, so it works the same on every platform.
+// watched directories also report files that are added or removed
+pub struct FileWatcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    directories: Vec<PathBuf>,
+    interval: Duration,
+    last_poll: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(interval: Duration) -> Self {
+        return FileWatcher { files: Vec::new(), directories: Vec::new(), interval, last_poll: Instant::now() };
+    }
+
+    pub fn watch<P>(&mut self, path: P)
+    where P: AsRef<Path>, {
+        let path: PathBuf = path.as_ref().to_path_buf();
+        if !self.files.iter().any(|(watched, _)| *watched == path) {
+            let modified: Option<SystemTime> = FileWatcher::modified(&path);
+            self.files.push((path, modified));
+        }
+    }
+
+    pub fn watch_directory<P>(&mut self, directory: P)
+    where P: AsRef<Path>, {
+        self.directories.push(directory.as_ref().to_path_buf());
+        for path in FileWatcher::list(directory.as_ref()) {
+            self.watch(path);
+        }
+    }
+
+    // files that were changed, added or removed since the last poll, checks at most once per interval
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < self.interval {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+        let mut changed: Vec<PathBuf> = Vec::new();
+        let directories: Vec<PathBuf> = self.directories.clone();
+        for directory in directories {
+            for path in FileWatcher::list(&directory) {
+                if !self.files.iter().any(|(watched, _)| *watched == path) {
+                    self.files.push((path.clone(), FileWatcher::modified(&path)));
+                    changed.push(path);
+                }
+            }
+        }
+        for (path, modified) in self.files.iter_mut() {
+            let now: Option<SystemTime> = FileWatcher::modified(path);
+            if now != *modified {
+                *modified = now;
+                changed.push(path.clone());
+            }
+        }
+        return changed;
+    }
+
+    fn modified(path: &Path) -> Option<SystemTime> {
+        return fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    }
+
+    fn list(directory: &Path) -> Vec<PathBuf> {
+        match fs::read_dir(directory) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).filter(|path| path.is_file()).collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}